@@ -0,0 +1,161 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    io::{BufRead, BufReader, Read},
+    str::FromStr,
+};
+
+use util::groups::{groups, GroupError};
+
+pub type Calories = u64;
+
+/// Summary of a list of calorie counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: Calories,
+    pub max: Calories,
+    pub mean: f64,
+    pub median: f64,
+}
+impl Stats {
+    /// Statistics of the given values, or `None` if there are none
+    pub fn of(values: &[Calories]) -> Option<Self> {
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        let (min, max) = (*sorted.first()?, *sorted.last()?);
+        let mid = sorted.len() / 2;
+        let median = if sorted.len() & 1 == 0 {
+            (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+        } else {
+            sorted[mid] as f64
+        };
+        Some(Stats {
+            min,
+            max,
+            mean: sorted.iter().sum::<Calories>() as f64 / sorted.len() as f64,
+            median,
+        })
+    }
+}
+
+/// The food items carried by a single elf
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Elf {
+    /// Position of the elf in the input, starting at 0
+    pub index: usize,
+    pub items: Vec<Calories>,
+}
+impl Elf {
+    pub fn total(&self) -> Calories {
+        self.items.iter().sum()
+    }
+
+    pub fn stats(&self) -> Option<Stats> {
+        Stats::of(&self.items)
+    }
+}
+
+/// All elves and the calories they carry
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Inventory {
+    elves: Vec<Elf>,
+}
+impl Inventory {
+    /// Parse an inventory line by line, without reading the whole input first
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, GroupError> {
+        Self::from_buf_read(BufReader::new(reader))
+    }
+
+    fn from_buf_read<R: BufRead>(reader: R) -> Result<Self, GroupError> {
+        let elves = groups(reader)
+            .enumerate()
+            .map(|(index, items)| items.map(|items| Elf { index, items }))
+            .collect::<Result<_, _>>()?;
+        Ok(Inventory { elves })
+    }
+
+    pub fn elves(&self) -> &[Elf] {
+        &self.elves
+    }
+
+    pub fn totals(&self) -> impl Iterator<Item = Calories> + '_ {
+        self.elves.iter().map(|e| e.total())
+    }
+
+    /// The `k` elves carrying the most calories, most first.
+    ///
+    /// Keeps a heap of at most `k` elves, so this doesn't sort the whole inventory
+    pub fn top(&self, k: usize) -> Vec<&Elf> {
+        let mut heap = BinaryHeap::with_capacity(k.min(self.elves.len()) + 1);
+        for (i, total) in self.totals().enumerate() {
+            // Earlier elves win ties
+            heap.push(Reverse((total, Reverse(i))));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((_, Reverse(i)))| &self.elves[i])
+            .collect()
+    }
+
+    /// Sum of the calories carried by the `k` elves carrying the most
+    pub fn top_total(&self, k: usize) -> Calories {
+        self.top(k).iter().map(|e| e.total()).sum()
+    }
+
+    /// Statistics over the total calories of every elf
+    pub fn stats(&self) -> Option<Stats> {
+        Stats::of(&self.totals().collect::<Vec<_>>())
+    }
+
+    /// Rank of the elf with the given index, 1 carrying the most. Elves with equal totals
+    /// share a rank
+    pub fn rank(&self, index: usize) -> Option<usize> {
+        let total = self.elves.get(index)?.total();
+        Some(self.totals().filter(|t| *t > total).count() + 1)
+    }
+
+    /// The elf at the given position when ordered by calories, 1 carrying the most
+    pub fn at_rank(&self, rank: usize) -> Option<&Elf> {
+        self.top(rank).into_iter().nth(rank.checked_sub(1)?)
+    }
+}
+impl FromStr for Inventory {
+    type Err = GroupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_buf_read(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+
+    #[test]
+    fn example() {
+        let inventory: Inventory = EXAMPLE.parse().unwrap();
+        assert_eq!(inventory.elves().len(), 5);
+        assert_eq!(inventory.top_total(1), 24000);
+        assert_eq!(inventory.top_total(3), 45000);
+        let top: Vec<usize> = inventory.top(10).iter().map(|e| e.index).collect();
+        assert_eq!(top, vec![3, 2, 4, 0, 1]);
+
+        assert_eq!(inventory.rank(4), Some(3));
+        assert_eq!(inventory.rank(5), None);
+        assert_eq!(inventory.at_rank(2).unwrap().index, 2);
+        assert_eq!(inventory.at_rank(0), None);
+        assert_eq!(inventory.top(usize::MAX).len(), 5);
+        assert_eq!(inventory.at_rank(usize::MAX), None);
+
+        let stats = inventory.stats().unwrap();
+        assert_eq!((stats.min, stats.max), (4000, 24000));
+        assert_eq!(stats.mean, 11000.0);
+        assert_eq!(stats.median, 10000.0);
+        assert_eq!(inventory.elves()[2].stats().unwrap().median, 5500.0);
+    }
+}
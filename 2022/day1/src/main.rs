@@ -1,27 +1,29 @@
-use rayon::prelude::*;
-use tokio::fs::read_to_string;
+use std::fs::File;
 
-#[tokio::main]
-async fn main() {
-    let input = read_to_string("input.txt").await.unwrap();
+use color_eyre::{
+    eyre::{Context, ContextCompat},
+    Result,
+};
+use day1::inventory::Inventory;
 
-    let inventories: Vec<_> = input.split("\n\n").collect();
+fn main() -> Result<()> {
+    color_eyre::install()?;
 
-    let mut calorie_counts = inventories
-        .par_iter()
-        .map(|elf_inventory| {
-            elf_inventory
-                .split('\n')
-                .map(|line| line.parse::<usize>().unwrap_or(0))
-                .sum::<usize>()
-        })
-        .collect::<Vec<_>>();
-    calorie_counts.sort_unstable();
+    let input = File::open("input.txt").wrap_err("Opening input.txt")?;
+    let inventory = Inventory::from_reader(input).wrap_err("Parsing the inventory")?;
 
-    let top_3 = &calorie_counts[calorie_counts.len() - 3..];
+    println!("Highest elf calorie count: {}", inventory.top_total(1));
+    println!("Highest 3 elf calorie counts: {}", inventory.top_total(3));
 
+    let stats = inventory.stats().wrap_err("No elves in the inventory")?;
     println!(
-        "Highest 3 elf calorie counts: {}",
-        top_3.iter().sum::<usize>()
+        "{} elves, carrying between {} and {} calories (mean {:.1}, median {:.1})",
+        inventory.elves().len(),
+        stats.min,
+        stats.max,
+        stats.mean,
+        stats.median
     );
+
+    Ok(())
 }
@@ -1,16 +1,29 @@
 use std::fmt::Display;
 
+use thiserror::Error;
+
 use log::{debug, trace};
 
+use crate::ocr::{Font, OcrError};
+
 const DARK: char = '.';
 const LIT: char = '#';
-const SPRITE_SIZE: u32 = 3;
-const LINE_LENGTH: u32 = 40;
-const HEIGHT: u32 = 6;
+/// Geometry of the 2022 day 10 screen
+pub const SPRITE_SIZE: u32 = 3;
+pub const LINE_LENGTH: u32 = 40;
+pub const HEIGHT: u32 = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Error)]
+#[error("Invalid CRT geometry: {width}x{height} with a sprite of size {sprite_size}")]
+pub struct GeometryError {
+    pub width: u32,
+    pub height: u32,
+    pub sprite_size: u32,
+}
 
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum BeamSymbol {
+pub enum BeamSymbol {
     Dark,
     Lit,
 }
@@ -39,39 +52,97 @@ impl Display for BeamSymbol {
 // Since our program doesn't have human eyes, let's write the Beam values into a framebuffer.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Crt {
+    width: u32,
+    height: u32,
+    sprite_size: u32,
     beam_pos: u32,
     line_pos: u32,
     fb: Vec<BeamSymbol>,
 }
 impl Crt {
     pub fn new() -> Self {
-        Crt {
+        Self::with_geometry(LINE_LENGTH, HEIGHT, SPRITE_SIZE).expect("Default geometry is valid")
+    }
+
+    /// Create a screen `width` pixels wide and `height` lines tall.
+    ///
+    /// The sprite covers `sprite_size` pixels around its position. For even sizes, the extra pixel
+    /// is on the right.
+    pub fn with_geometry(width: u32, height: u32, sprite_size: u32) -> Result<Self, GeometryError> {
+        if width == 0 || height == 0 || sprite_size == 0 {
+            return Err(GeometryError {
+                width,
+                height,
+                sprite_size,
+            });
+        }
+        Ok(Crt {
+            width,
+            height,
+            sprite_size,
             beam_pos: 0,
             line_pos: 0,
-            fb: vec![BeamSymbol::Dark; (LINE_LENGTH * HEIGHT) as usize],
+            fb: vec![BeamSymbol::Dark; (width * height) as usize],
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn sprite_size(&self) -> u32 {
+        self.sprite_size
+    }
+    /// Position of the beam as `(x, y)`, which is the next pixel to be drawn
+    pub fn beam(&self) -> (u32, u32) {
+        (self.beam_pos, self.line_pos)
+    }
+    /// The framebuffer, row by row
+    pub fn pixels(&self) -> &[BeamSymbol] {
+        &self.fb
+    }
+    pub fn pixel(&self, x: u32, y: u32) -> Option<BeamSymbol> {
+        if x >= self.width || y >= self.height {
+            return None;
         }
+        Some(self.fb[(x + y * self.width) as usize])
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[BeamSymbol]> {
+        self.fb.chunks_exact(self.width as usize)
     }
-    pub fn tick(&mut self, sprite_pos: i32) {
+
+    /// Draw the pixel under the beam and advance it. Returns the drawn pixel
+    pub fn tick(&mut self, sprite_pos: i64) -> BeamSymbol {
+        let sprite_start = sprite_pos - ((self.sprite_size - 1) / 2) as i64;
+        let sprite_end = sprite_start + self.sprite_size as i64 - 1;
         debug!(
             "Sprite range: {}->{}, beam at {}",
-            sprite_pos - (SPRITE_SIZE / 2) as i32,
-            sprite_pos + (SPRITE_SIZE / 2) as i32,
-            self.beam_pos
+            sprite_start, sprite_end, self.beam_pos
         );
-        let fb_pos = (self.beam_pos + (self.line_pos * LINE_LENGTH)) as usize;
-        if sprite_pos.abs_diff(self.beam_pos as i32) <= SPRITE_SIZE / 2 {
+        let fb_pos = (self.beam_pos + (self.line_pos * self.width)) as usize;
+        let symbol = if (sprite_start..=sprite_end).contains(&(self.beam_pos as i64)) {
             trace!("Lighting up framebuffer position: {}", fb_pos);
-            let _ = std::mem::replace(&mut self.fb[fb_pos], BeamSymbol::Lit);
+            BeamSymbol::Lit
         } else {
             trace!("Darkening up framebuffer position: {}", fb_pos);
-            let _ = std::mem::replace(&mut self.fb[fb_pos], BeamSymbol::Dark);
-        }
+            BeamSymbol::Dark
+        };
+        self.fb[fb_pos] = symbol;
         let old_beam = self.beam_pos;
-        self.beam_pos = (self.beam_pos + 1) % LINE_LENGTH;
-        if old_beam > self.beam_pos {
+        self.beam_pos = (self.beam_pos + 1) % self.width;
+        if old_beam >= self.beam_pos {
             // Wrapped End of line
-            self.line_pos = (self.line_pos + 1) % HEIGHT;
+            self.line_pos = (self.line_pos + 1) % self.height;
         }
+        symbol
+    }
+
+    /// Read the letters on the screen, using the font that matches the screen height
+    pub fn read_text(&self) -> Result<String, OcrError> {
+        let bitmap: Vec<bool> = self.fb.iter().map(|s| *s == BeamSymbol::Lit).collect();
+        Font::for_height(self.height as usize)?.read(&bitmap, self.width as usize)
     }
 }
 impl Default for Crt {
@@ -81,7 +152,7 @@ impl Default for Crt {
 }
 impl Display for Crt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for line in self.fb.chunks_exact(LINE_LENGTH as usize) {
+        for line in self.rows() {
             write!(f, "|")?;
             for c in line {
                 write!(f, "{}", c)?
@@ -91,3 +162,29 @@ impl Display for Crt {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_geometry() {
+        assert!(Crt::with_geometry(0, 6, 3).is_err());
+        let mut crt = Crt::with_geometry(5, 2, 2).unwrap();
+        // A sprite of size 2 at 1 covers pixels 1 and 2
+        for _ in 0..5 {
+            crt.tick(1);
+        }
+        assert_eq!(crt.beam(), (0, 1));
+        let row: String = crt
+            .rows()
+            .next()
+            .unwrap()
+            .iter()
+            .map(|&s| char::from(s))
+            .collect();
+        assert_eq!(row, ".##..");
+        assert_eq!(crt.pixel(2, 0), Some(BeamSymbol::Lit));
+        assert_eq!(crt.pixel(5, 0), None);
+    }
+}
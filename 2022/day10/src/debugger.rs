@@ -0,0 +1,507 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::{self, BufRead, Write},
+};
+
+use thiserror::Error;
+
+use crate::{
+    crt::Crt,
+    interpreter::{Interpreter, InterpreterError, Register},
+};
+
+#[derive(Debug, Error)]
+pub enum DebuggerError {
+    #[error("Unknown command `{0}`, try `help`")]
+    UnknownCommand(String),
+    #[error("Invalid arguments for `{0}`")]
+    BadArguments(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Breakpoint {
+    /// Stop before the given cycle (starting at 1) executes
+    Cycle(usize),
+    /// Stop before the instruction at the given index starts
+    Instruction(usize),
+}
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Cycle(c) => write!(f, "cycle {}", c),
+            Breakpoint::Instruction(i) => write!(f, "instruction {}", i),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Watchpoint {
+    /// Stop whenever the register changes
+    Changed(Register),
+    /// Stop when the register takes on the given value
+    Equals(Register, i64),
+}
+impl Display for Watchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Watchpoint::Changed(r) => write!(f, "{}", r),
+            Watchpoint::Equals(r, v) => write!(f, "{} == {}", r, v),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StopReason {
+    /// A single step has completed
+    Step,
+    Breakpoint(Breakpoint),
+    Watchpoint {
+        watch: Watchpoint,
+        old: i64,
+        new: i64,
+    },
+    /// The program stopped with an error
+    Fault(InterpreterError),
+    Finished,
+}
+impl Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Step => write!(f, "Stepped"),
+            StopReason::Breakpoint(b) => write!(f, "Hit breakpoint at {}", b),
+            StopReason::Watchpoint { watch, old, new } => {
+                write!(f, "Watchpoint {} triggered: {} -> {}", watch, old, new)
+            }
+            StopReason::Fault(e) => write!(f, "Program faulted: {}", e),
+            StopReason::Finished => write!(f, "Program finished"),
+        }
+    }
+}
+
+/// How far to run before stopping
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RunMode {
+    Cycle,
+    Instruction,
+    Continue,
+}
+
+/// Drives an [`Interpreter`] and its [`Crt`] cycle by cycle, stopping at breakpoints and
+/// watchpoints.
+///
+/// Every executed cycle can be written to a trace as one JSON object per line.
+pub struct Debugger {
+    interpreter: Interpreter,
+    crt: Crt,
+    cycle: usize,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    /// The cycle at which the last breakpoint stopped execution, so it isn't hit again
+    stopped_at: Option<usize>,
+    trace: Option<Box<dyn Write>>,
+}
+impl Debugger {
+    pub fn new(interpreter: Interpreter, crt: Crt) -> Self {
+        Debugger {
+            interpreter,
+            crt,
+            cycle: 1,
+            breakpoints: vec![],
+            watchpoints: vec![],
+            stopped_at: None,
+            trace: None,
+        }
+    }
+
+    /// Write a JSON line for every executed cycle to `writer`
+    pub fn set_trace(&mut self, writer: Box<dyn Write>) {
+        self.trace = Some(writer);
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+    pub fn crt(&self) -> &Crt {
+        &self.crt
+    }
+    /// The cycle that executes next, starting at 1
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+    }
+
+    /// Run until `mode` says to stop. Breakpoints are checked before every cycle, except for the
+    /// one execution last stopped at, so they also apply to the very first cycle
+    pub fn run(&mut self, mode: RunMode) -> io::Result<StopReason> {
+        let mut executed = false;
+        loop {
+            if self.interpreter.is_finished() {
+                return Ok(StopReason::Finished);
+            }
+            if self.stopped_at != Some(self.cycle) {
+                if let Some(b) = self.breakpoint_hit() {
+                    self.stopped_at = Some(self.cycle);
+                    return Ok(StopReason::Breakpoint(b));
+                }
+            }
+            let stepped = match mode {
+                RunMode::Cycle => executed,
+                RunMode::Instruction => executed && self.interpreter.at_instruction_boundary(),
+                RunMode::Continue => false,
+            };
+            if stepped {
+                return Ok(StopReason::Step);
+            }
+            if let Some(reason) = self.execute_cycle()? {
+                return Ok(reason);
+            }
+            executed = true;
+        }
+    }
+
+    fn breakpoint_hit(&self) -> Option<Breakpoint> {
+        self.breakpoints.iter().copied().find(|b| match b {
+            Breakpoint::Cycle(c) => *c == self.cycle,
+            Breakpoint::Instruction(i) => {
+                self.interpreter.at_instruction_boundary() && *i == self.interpreter.pc()
+            }
+        })
+    }
+
+    /// Run one cycle on the CRT and CPU. Returns a fault or the first watchpoint that triggered
+    fn execute_cycle(&mut self) -> io::Result<Option<StopReason>> {
+        let before: BTreeMap<Register, i64> = self.interpreter.registers().collect();
+        let instruction = self.interpreter.current_instruction();
+        let pixel = self.crt.tick(self.interpreter.x());
+
+        if let Some(trace) = &mut self.trace {
+            let registers = before
+                .iter()
+                .map(|(r, v)| format!("\"{}\":{}", json_escape(&r.to_string()), v))
+                .collect::<Vec<_>>()
+                .join(",");
+            writeln!(
+                trace,
+                "{{\"cycle\":{},\"pc\":{},\"instruction\":{},\"registers\":{{{}}},\"pixel\":\"{}\"}}",
+                self.cycle,
+                self.interpreter.pc(),
+                instruction
+                    .map(|i| format!("\"{}\"", json_escape(&i.cmd.to_string())))
+                    .unwrap_or_else(|| "null".to_string()),
+                registers,
+                json_escape(&pixel.to_string())
+            )?;
+        }
+
+        let result = self.interpreter.tick();
+        self.cycle += 1;
+        if let Err(e) = result {
+            return Ok(Some(StopReason::Fault(e)));
+        }
+
+        for watch in &self.watchpoints {
+            let (r, target) = match watch {
+                Watchpoint::Changed(r) => (r, None),
+                Watchpoint::Equals(r, v) => (r, Some(*v)),
+            };
+            let old = before.get(r).copied().unwrap_or(0);
+            let new = self.interpreter.register(*r);
+            let triggered = match target {
+                None => old != new,
+                Some(v) => old != v && new == v,
+            };
+            if triggered {
+                return Ok(Some(StopReason::Watchpoint {
+                    watch: *watch,
+                    old,
+                    new,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Execute one debugger command, writing its output to `out`. Returns false on `quit`.
+    ///
+    /// Commands:
+    /// - `break cycle <n>`, `break instr <n>`: add a breakpoint
+    /// - `watch <reg>`, `watch <reg> == <value>`: add a watchpoint
+    /// - `clear`: remove all breakpoints and watchpoints
+    /// - `tick [n]`: run n cycles
+    /// - `step [n]`: run n instructions
+    /// - `continue`: run until a breakpoint, watchpoint or the end of the program
+    /// - `regs`, `print <reg>`, `screen`: show the machine state
+    /// - `quit`
+    pub fn execute(&mut self, command: &str, out: &mut impl Write) -> Result<bool, DebuggerError> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let bad_args = || DebuggerError::BadArguments(command.trim().to_string());
+        let count = |i: usize| -> Result<usize, DebuggerError> {
+            words
+                .get(i)
+                .map(|w| w.parse().map_err(|_| bad_args()))
+                .unwrap_or(Ok(1))
+        };
+        match words.as_slice() {
+            [] => {}
+            ["break" | "b", kind, n] => {
+                let n = n.parse().map_err(|_| bad_args())?;
+                let b = match *kind {
+                    "cycle" => Breakpoint::Cycle(n),
+                    "instr" => Breakpoint::Instruction(n),
+                    _ => return Err(bad_args()),
+                };
+                self.add_breakpoint(b);
+                writeln!(out, "Breakpoint at {}", b)?;
+            }
+            ["watch" | "w", reg, rest @ ..] => {
+                let r = Register::from_name(reg).ok_or_else(bad_args)?;
+                let w = match rest {
+                    [] => Watchpoint::Changed(r),
+                    ["==", v] => Watchpoint::Equals(r, v.parse().map_err(|_| bad_args())?),
+                    _ => return Err(bad_args()),
+                };
+                self.add_watchpoint(w);
+                writeln!(out, "Watching {}", w)?;
+            }
+            ["clear"] => self.clear(),
+            ["tick" | "t", ..] | ["step" | "s", ..] | ["continue" | "c"] => {
+                let (mode, n) = match words[0] {
+                    "tick" | "t" => (RunMode::Cycle, count(1)?),
+                    "step" | "s" => (RunMode::Instruction, count(1)?),
+                    _ => (RunMode::Continue, 1),
+                };
+                let mut reason = StopReason::Step;
+                for _ in 0..n {
+                    reason = self.run(mode)?;
+                    if reason != StopReason::Step {
+                        break;
+                    }
+                }
+                writeln!(out, "{}", reason)?;
+                self.write_location(out)?;
+            }
+            ["regs" | "r"] => self.write_location(out)?,
+            ["print" | "p", reg] => {
+                let r = Register::from_name(reg).ok_or_else(bad_args)?;
+                writeln!(out, "{} = {}", r, self.interpreter.register(r))?;
+            }
+            ["screen"] => write!(out, "{}", self.crt)?,
+            ["quit" | "q"] => return Ok(false),
+            ["help" | "h"] => writeln!(
+                out,
+                "break cycle|instr <n>, watch <reg> [== <value>], clear, tick [n], step [n], \
+                 continue, regs, print <reg>, screen, quit"
+            )?,
+            _ => return Err(DebuggerError::UnknownCommand(command.trim().to_string())),
+        }
+        Ok(true)
+    }
+
+    /// Run commands from a script, one per line. Lines starting with `#` are ignored
+    pub fn run_script(&mut self, script: &str, out: &mut impl Write) -> Result<(), DebuggerError> {
+        for line in script.lines() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            if !self.execute(line, out)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read commands from `input` until it ends or `quit` is entered. Errors in commands are
+    /// reported and don't end the session
+    pub fn run_interactive(
+        &mut self,
+        input: impl BufRead,
+        out: &mut impl Write,
+    ) -> Result<(), DebuggerError> {
+        write!(out, "(dbg) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            match self.execute(&line?, out) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(DebuggerError::Io(e)) => return Err(e.into()),
+                Err(e) => writeln!(out, "{}", e)?,
+            }
+            write!(out, "(dbg) ")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_location(&self, out: &mut impl Write) -> io::Result<()> {
+        let registers = self
+            .interpreter
+            .registers()
+            .map(|(r, v)| format!("{}={}", r, v))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let instruction = self
+            .interpreter
+            .current_instruction()
+            .map(|i| i.cmd.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        writeln!(
+            out,
+            "cycle {} pc {} [{}] {}",
+            self.cycle,
+            self.interpreter.pc(),
+            instruction,
+            registers
+        )
+    }
+}
+
+/// Escape a string for use inside JSON quotes
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::command_list;
+
+    fn debugger(source: &str) -> Debugger {
+        let program = command_list(source).unwrap();
+        Debugger::new(Interpreter::with_input(&program), Crt::new())
+    }
+
+    #[test]
+    fn breakpoints_and_stepping() {
+        let mut dbg = debugger("noop\naddx 3\naddx -5\n");
+        dbg.add_breakpoint(Breakpoint::Cycle(3));
+        assert_eq!(
+            dbg.run(RunMode::Continue).unwrap(),
+            StopReason::Breakpoint(Breakpoint::Cycle(3))
+        );
+        // During cycle 3, addx 3 is still executing
+        assert_eq!(dbg.interpreter().x(), 1);
+        assert_eq!(dbg.run(RunMode::Cycle).unwrap(), StopReason::Step);
+        assert_eq!(dbg.interpreter().x(), 4);
+
+        let mut dbg = debugger("noop\naddx 3\naddx -5\n");
+        dbg.add_breakpoint(Breakpoint::Instruction(2));
+        assert_eq!(
+            dbg.run(RunMode::Continue).unwrap(),
+            StopReason::Breakpoint(Breakpoint::Instruction(2))
+        );
+        assert_eq!(dbg.cycle(), 4);
+        assert_eq!(dbg.run(RunMode::Instruction).unwrap(), StopReason::Finished);
+        assert_eq!(dbg.interpreter().x(), -1);
+    }
+
+    #[test]
+    fn breakpoints_before_the_first_cycle() {
+        let mut dbg = debugger("noop\naddx 3\n");
+        dbg.add_breakpoint(Breakpoint::Cycle(1));
+        dbg.add_breakpoint(Breakpoint::Instruction(0));
+        assert_eq!(
+            dbg.run(RunMode::Continue).unwrap(),
+            StopReason::Breakpoint(Breakpoint::Cycle(1))
+        );
+        assert_eq!(dbg.cycle(), 1);
+        // Continuing leaves the breakpoint that stopped execution behind
+        assert_eq!(dbg.run(RunMode::Continue).unwrap(), StopReason::Finished);
+
+        let mut dbg = debugger("noop\naddx 3\n");
+        dbg.add_breakpoint(Breakpoint::Instruction(0));
+        assert_eq!(
+            dbg.run(RunMode::Instruction).unwrap(),
+            StopReason::Breakpoint(Breakpoint::Instruction(0))
+        );
+        assert_eq!(dbg.cycle(), 1);
+        // Stepping stops at a breakpoint on the next instruction boundary
+        dbg.add_breakpoint(Breakpoint::Instruction(1));
+        assert_eq!(
+            dbg.run(RunMode::Instruction).unwrap(),
+            StopReason::Breakpoint(Breakpoint::Instruction(1))
+        );
+        assert_eq!(dbg.cycle(), 2);
+    }
+
+    #[test]
+    fn json_escapes_control_characters() {
+        assert_eq!(json_escape("a\"b\\c"), "a\\\"b\\\\c");
+        assert_eq!(json_escape("\n\t\u{1}"), "\\n\\t\\u0001");
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut dbg = debugger("noop\naddx 3\naddx -5\n");
+        dbg.add_watchpoint(Watchpoint::Equals(Register::X, -1));
+        assert_eq!(
+            dbg.run(RunMode::Continue).unwrap(),
+            StopReason::Watchpoint {
+                watch: Watchpoint::Equals(Register::X, -1),
+                old: 4,
+                new: -1
+            }
+        );
+        assert_eq!(dbg.cycle(), 6);
+    }
+
+    #[test]
+    fn script_and_trace() {
+        let mut dbg = debugger("noop\naddx 3\naddx -5\n");
+        let mut out = Vec::new();
+        dbg.run_script("watch x\ncontinue\nprint x\nquit\ntick", &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Watchpoint x triggered: 1 -> 4"));
+        assert!(out.contains("x = 4"));
+        assert_eq!(dbg.cycle(), 4);
+
+        let mut dbg = debugger("noop\naddx 3\n");
+        let trace = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        dbg.set_trace(Box::new(SharedBuffer(trace.clone())));
+        dbg.run(RunMode::Continue).unwrap();
+        let trace = String::from_utf8(trace.borrow().clone()).unwrap();
+        assert_eq!(
+            trace.lines().collect::<Vec<_>>(),
+            vec![
+                r##"{"cycle":1,"pc":0,"instruction":"NOP","registers":{"x":1},"pixel":"#"}"##,
+                r##"{"cycle":2,"pc":1,"instruction":"ADD x 3","registers":{"x":1},"pixel":"#"}"##,
+                r##"{"cycle":3,"pc":1,"instruction":"ADD x 3","registers":{"x":1},"pixel":"#"}"##,
+            ]
+        );
+    }
+
+    struct SharedBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, ImageResult, Rgba, RgbaImage,
+};
+
+use crate::crt::{BeamSymbol, Crt};
+
+const DARK: Rgba<u8> = Rgba([16, 16, 16, 255]);
+const LIT: Rgba<u8> = Rgba([64, 255, 64, 255]);
+const BEAM: Rgba<u8> = Rgba([255, 64, 64, 255]);
+
+/// Render the screen, with every pixel drawn as a `scale` x `scale` square
+pub fn render(crt: &Crt, scale: u32) -> RgbaImage {
+    let scale = scale.max(1);
+    let color = |x, y| match crt.pixel(x / scale, y / scale) {
+        Some(BeamSymbol::Lit) => LIT,
+        _ => DARK,
+    };
+    RgbaImage::from_fn(crt.width() * scale, crt.height() * scale, color)
+}
+
+/// Save the screen to an image file, the format is picked from the file extension
+pub fn save_screen(crt: &Crt, path: impl AsRef<Path>, scale: u32) -> ImageResult<()> {
+    render(crt, scale).save(path)
+}
+
+/// Records a frame for every tick of the beam, to export the sweep as an animation
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    scale: u32,
+    frames: Vec<RgbaImage>,
+}
+impl Recorder {
+    pub fn new(scale: u32) -> Self {
+        Recorder {
+            scale: scale.max(1),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Capture the current screen, with the beam position highlighted
+    pub fn capture(&mut self, crt: &Crt) {
+        let mut frame = render(crt, self.scale);
+        let (bx, by) = crt.beam();
+        for dy in 0..self.scale {
+            for dx in 0..self.scale {
+                frame.put_pixel(bx * self.scale + dx, by * self.scale + dy, BEAM);
+            }
+        }
+        self.frames.push(frame);
+    }
+
+    pub fn frames(&self) -> &[RgbaImage] {
+        &self.frames
+    }
+
+    /// Save all frames as numbered images `frame_0000.png`, ... in `dir`
+    pub fn save_frames(&self, dir: impl AsRef<Path>) -> ImageResult<()> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            frame.save(dir.as_ref().join(format!("frame_{:04}.png", i)))?;
+        }
+        Ok(())
+    }
+
+    /// Save all frames as a looping GIF, showing each frame for `delay_ms` milliseconds
+    pub fn save_gif(&self, path: impl AsRef<Path>, delay_ms: u32) -> ImageResult<()> {
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(
+            self.frames.iter().map(|f| {
+                Frame::from_parts(f.clone(), 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_and_record() {
+        let mut crt = Crt::with_geometry(4, 2, 1).unwrap();
+        let mut recorder = Recorder::new(2);
+        for sprite in [0, 5, 2, 3, 0, 0, 0, 3] {
+            crt.tick(sprite);
+            recorder.capture(&crt);
+        }
+        let image = render(&crt, 2);
+        assert_eq!(image.dimensions(), (8, 4));
+        assert_eq!(*image.get_pixel(1, 1), LIT);
+        assert_eq!(*image.get_pixel(2, 0), DARK);
+        assert_eq!(*image.get_pixel(7, 3), LIT);
+        assert_eq!(recorder.frames().len(), 8);
+        // After the first tick, the beam is on the second pixel
+        assert_eq!(*recorder.frames()[0].get_pixel(2, 0), BEAM);
+    }
+}
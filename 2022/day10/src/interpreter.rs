@@ -1,61 +1,357 @@
-use std::{collections::VecDeque, fmt::Display, vec};
+use std::{collections::BTreeMap, fmt::Display};
 
 use log::debug;
+use thiserror::Error;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Error)]
+pub enum InterpreterError {
+    #[error("Instruction {pc} `{cmd}` overflows register {register}")]
+    Overflow {
+        pc: usize,
+        cmd: Command,
+        register: Register,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Register(pub char);
+impl Register {
+    pub const X: Register = Register('x');
+
+    /// Registers are named by a single letter
+    pub fn from_name(name: &str) -> Option<Register> {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(Register(c)),
+            _ => None,
+        }
+    }
+}
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A source value for an instruction, either read from a register or given directly
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Operand {
+    Register(Register),
+    Immediate(i64),
+}
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Register(r) => write!(f, "{}", r),
+            Operand::Immediate(v) => write!(f, "{}", v),
+        }
+    }
+}
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Command {
     Noop,
-    Addx(i32),
+    /// Add the operand to the register
+    Add(Register, Operand),
+    /// Multiply the register by the operand
+    Mul(Register, Operand),
+    /// Copy the operand into the register
+    Set(Register, Operand),
+    /// Continue at the given instruction index
+    Jump(isize),
+    /// Jump if the operand is zero
+    JumpIfZero(Operand, isize),
+    /// Jump if the operand is not zero
+    JumpIfNotZero(Operand, isize),
+    /// Stop the program
+    Halt,
 }
 impl Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let out = match self {
             Command::Noop => "NOP".to_string(),
-            Command::Addx(x) => format!("ADDX {}", x),
+            Command::Add(r, v) => format!("ADD {} {}", r, v),
+            Command::Mul(r, v) => format!("MUL {} {}", r, v),
+            Command::Set(r, v) => format!("SET {} {}", r, v),
+            Command::Jump(t) => format!("JMP @{}", t),
+            Command::JumpIfZero(v, t) => format!("JZ {} @{}", v, t),
+            Command::JumpIfNotZero(v, t) => format!("JNZ {} @{}", v, t),
+            Command::Halt => "HALT".to_string(),
         };
         write!(f, "{}", out)
     }
 }
 
+/// A command together with the number of cycles it takes to execute
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Instruction {
+    pub cmd: Command,
+    pub cycles: u32,
+}
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} cycles)", self.cmd, self.cycles)
+    }
+}
+
+/// The kind of argument an opcode expects in the assembly source
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OperandKind {
+    /// A register name, such as `x`
+    Register,
+    /// A register name or an integer
+    Value,
+    /// A label or a relative offset such as `+3`, `3` or `-2`
+    Target,
+}
+
+/// An operand after parsing, as handed to [`Opcode::build`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Arg {
+    Register(Register),
+    Value(Operand),
+    /// Absolute instruction index
+    Target(isize),
+}
+impl Arg {
+    /// Unwrap the register, [`Opcode::build`] is only called with the operands it declared
+    pub fn register(&self) -> Register {
+        match self {
+            Arg::Register(r) => *r,
+            _ => panic!("Expected register argument, got {:?}", self),
+        }
+    }
+    pub fn value(&self) -> Operand {
+        match self {
+            Arg::Value(v) => *v,
+            Arg::Register(r) => Operand::Register(*r),
+            _ => panic!("Expected value argument, got {:?}", self),
+        }
+    }
+    pub fn target(&self) -> isize {
+        match self {
+            Arg::Target(t) => *t,
+            _ => panic!("Expected jump target, got {:?}", self),
+        }
+    }
+}
+
+/// One entry in the instruction table
+#[derive(Clone, Copy, Debug)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub operands: &'static [OperandKind],
+    pub cycles: u32,
+    pub build: fn(&[Arg]) -> Command,
+}
+
+/// The table of opcodes known to the assembler.
+///
+/// The default set contains the two day10 instructions plus a few general purpose ones. Programs
+/// from other puzzles can be run by inserting their opcodes as aliases onto the same commands.
+#[derive(Clone, Debug)]
+pub struct InstructionSet {
+    opcodes: BTreeMap<&'static str, Opcode>,
+}
+impl InstructionSet {
+    pub fn empty() -> Self {
+        InstructionSet {
+            opcodes: BTreeMap::new(),
+        }
+    }
+
+    /// Add or replace an opcode
+    pub fn insert(&mut self, opcode: Opcode) -> Option<Opcode> {
+        self.opcodes.insert(opcode.mnemonic, opcode)
+    }
+
+    pub fn get(&self, mnemonic: &str) -> Option<&Opcode> {
+        self.opcodes.get(mnemonic)
+    }
+
+    pub fn opcodes(&self) -> impl Iterator<Item = &Opcode> {
+        self.opcodes.values()
+    }
+}
+impl Default for InstructionSet {
+    fn default() -> Self {
+        use OperandKind::{Target, Value};
+        let mut set = InstructionSet::empty();
+        for opcode in [
+            Opcode {
+                mnemonic: "noop",
+                operands: &[],
+                cycles: 1,
+                build: |_| Command::Noop,
+            },
+            Opcode {
+                mnemonic: "addx",
+                operands: &[Value],
+                cycles: 2,
+                build: |a| Command::Add(Register::X, a[0].value()),
+            },
+            Opcode {
+                mnemonic: "add",
+                operands: &[OperandKind::Register, Value],
+                cycles: 2,
+                build: |a| Command::Add(a[0].register(), a[1].value()),
+            },
+            Opcode {
+                mnemonic: "mul",
+                operands: &[OperandKind::Register, Value],
+                cycles: 2,
+                build: |a| Command::Mul(a[0].register(), a[1].value()),
+            },
+            Opcode {
+                mnemonic: "set",
+                operands: &[OperandKind::Register, Value],
+                cycles: 1,
+                build: |a| Command::Set(a[0].register(), a[1].value()),
+            },
+            Opcode {
+                mnemonic: "jmp",
+                operands: &[Target],
+                cycles: 1,
+                build: |a| Command::Jump(a[0].target()),
+            },
+            Opcode {
+                mnemonic: "jz",
+                operands: &[Value, Target],
+                cycles: 1,
+                build: |a| Command::JumpIfZero(a[0].value(), a[1].target()),
+            },
+            Opcode {
+                mnemonic: "jnz",
+                operands: &[Value, Target],
+                cycles: 1,
+                build: |a| Command::JumpIfNotZero(a[0].value(), a[1].target()),
+            },
+            Opcode {
+                mnemonic: "halt",
+                operands: &[],
+                cycles: 1,
+                build: |_| Command::Halt,
+            },
+        ] {
+            set.insert(opcode);
+        }
+        set
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Interpreter {
     cpu: Cpu,
-    input: VecDeque<Command>,
+    program: Vec<Instruction>,
+    pc: usize,
+    halted: bool,
 }
 impl Interpreter {
     pub fn new() -> Self {
         Interpreter::with_input(vec![].as_slice())
     }
 
-    pub fn with_input(input: &[Command]) -> Self {
+    pub fn with_input(input: &[Instruction]) -> Self {
         Interpreter {
             cpu: Cpu::new(),
-            input: input.iter().copied().collect::<VecDeque<_>>(),
+            program: input.to_vec(),
+            pc: 0,
+            halted: false,
         }
     }
 
-    pub fn x(&self) -> i32 {
-        self.cpu.x
+    pub fn x(&self) -> i64 {
+        self.register(Register::X)
+    }
+
+    pub fn register(&self, r: Register) -> i64 {
+        self.cpu.read(r)
+    }
+
+    pub fn set_register(&mut self, r: Register, value: i64) {
+        self.cpu.registers.insert(r, value);
+    }
+
+    /// All registers that have been written to, in alphabetical order
+    pub fn registers(&self) -> impl Iterator<Item = (Register, i64)> + '_ {
+        self.cpu.registers.iter().map(|(r, v)| (*r, *v))
     }
 
-    pub fn push_command(&mut self, cmd: Command) {
-        self.input.push_back(cmd);
+    /// Index of the instruction that is executing or will execute next
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// The instruction that executes during the next cycle, if any
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        if self.cpu.busy() {
+            Some(self.cpu.current)
+        } else if self.is_finished() {
+            None
+        } else {
+            self.program.get(self.pc).copied()
+        }
+    }
+
+    /// True if the next cycle starts a new instruction
+    pub fn at_instruction_boundary(&self) -> bool {
+        !self.cpu.busy()
+    }
+
+    pub fn push_command(&mut self, instruction: Instruction) {
+        self.program.push(instruction);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.halted || (!self.cpu.busy() && self.pc >= self.program.len())
     }
 
     /// Tick the CPU forward by one clock cycle. Returns true when the program has finished.
-    pub fn tick(&mut self) -> bool {
-        if self.input.is_empty() {
+    ///
+    /// An instruction that overflows a register halts the program with an error
+    pub fn tick(&mut self) -> Result<bool, InterpreterError> {
+        if self.is_finished() {
             debug!("Program has finished executing");
-            return true;
+            return Ok(true);
         }
         if !self.cpu.busy() {
-            self.cpu
-                .set_command(self.input.pop_front().unwrap())
-                .unwrap();
-        } else {
+            self.cpu.set_command(self.program[self.pc]).unwrap();
         }
-        self.cpu.tick();
-        false
+        let flow = match self.cpu.tick() {
+            Ok(flow) => flow,
+            Err(CpuError::Overflow(register)) => {
+                self.halted = true;
+                return Err(InterpreterError::Overflow {
+                    pc: self.pc,
+                    cmd: self.cpu.current.cmd,
+                    register,
+                });
+            }
+            Err(CpuError::Busy) => unreachable!("Ticking never sets a new command"),
+        };
+        match flow {
+            None => {}
+            Some(Flow::Next) => self.pc += 1,
+            Some(Flow::Jump(target)) => match usize::try_from(target) {
+                Ok(target) => self.pc = target,
+                Err(_) => {
+                    debug!("Jump to {} leaves the program", target);
+                    self.halted = true;
+                }
+            },
+            Some(Flow::Halt) => self.halted = true,
+        }
+        Ok(self.is_finished())
+    }
+
+    /// Run the program until it finishes. Returns the number of cycles taken
+    pub fn run(&mut self) -> Result<usize, InterpreterError> {
+        let mut cycles = 0;
+        while !self.is_finished() {
+            self.tick()?;
+            cycles += 1;
+        }
+        Ok(cycles)
     }
 }
 impl Default for Interpreter {
@@ -67,49 +363,95 @@ impl Default for Interpreter {
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 enum CpuError {
     Busy,
+    Overflow(Register),
 }
+
+/// What to do after an instruction has finished
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+enum Flow {
+    Next,
+    Jump(isize),
+    Halt,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 struct Cpu {
-    x: i32,
-    current_cmd: Command,
+    registers: BTreeMap<Register, i64>,
+    current: Instruction,
     cycles_left: u32,
 }
 impl Cpu {
     fn new() -> Self {
         Cpu {
-            x: 1,
-            current_cmd: Command::Noop,
+            registers: BTreeMap::from([(Register::X, 1)]),
+            current: Instruction {
+                cmd: Command::Noop,
+                cycles: 0,
+            },
             cycles_left: 0,
         }
     }
 
-    fn set_command(&mut self, cmd: Command) -> Result<(), CpuError> {
+    fn read(&self, r: Register) -> i64 {
+        self.registers.get(&r).copied().unwrap_or(0)
+    }
+
+    fn eval(&self, operand: Operand) -> i64 {
+        match operand {
+            Operand::Register(r) => self.read(r),
+            Operand::Immediate(v) => v,
+        }
+    }
+
+    fn set_command(&mut self, instruction: Instruction) -> Result<(), CpuError> {
         if self.cycles_left > 0 {
             return Err(CpuError::Busy);
         }
-        self.current_cmd = cmd;
-        self.cycles_left = match cmd {
-            Command::Noop => 1,
-            Command::Addx(_) => 2,
-        };
-        debug!(
-            "New CPU command ({} cycles): {}",
-            self.cycles_left, self.current_cmd
-        );
+        self.current = instruction;
+        // Every instruction occupies at least one cycle
+        self.cycles_left = instruction.cycles.max(1);
+        debug!("New CPU command: {}", self.current);
         Ok(())
     }
 
-    fn tick(&mut self) {
+    /// Returns the control flow once the current instruction has finished
+    fn tick(&mut self) -> Result<Option<Flow>, CpuError> {
         self.cycles_left -= 1;
-        if self.cycles_left == 0 {
-            debug!("Command {} has finished", self.current_cmd);
-            match self.current_cmd {
-                Command::Noop => {}
-                Command::Addx(y) => self.x += y,
-            }
-        } else {
-            debug!("Command {} still processing", self.current_cmd);
+        if self.cycles_left > 0 {
+            debug!("Command {} still processing", self.current.cmd);
+            return Ok(None);
         }
+        debug!("Command {} has finished", self.current.cmd);
+        let flow = match self.current.cmd {
+            Command::Noop => Flow::Next,
+            Command::Add(r, v) => {
+                let value = self
+                    .read(r)
+                    .checked_add(self.eval(v))
+                    .ok_or(CpuError::Overflow(r))?;
+                self.registers.insert(r, value);
+                Flow::Next
+            }
+            Command::Mul(r, v) => {
+                let value = self
+                    .read(r)
+                    .checked_mul(self.eval(v))
+                    .ok_or(CpuError::Overflow(r))?;
+                self.registers.insert(r, value);
+                Flow::Next
+            }
+            Command::Set(r, v) => {
+                let value = self.eval(v);
+                self.registers.insert(r, value);
+                Flow::Next
+            }
+            Command::Jump(t) => Flow::Jump(t),
+            Command::JumpIfZero(v, t) if self.eval(v) == 0 => Flow::Jump(t),
+            Command::JumpIfNotZero(v, t) if self.eval(v) != 0 => Flow::Jump(t),
+            Command::JumpIfZero(..) | Command::JumpIfNotZero(..) => Flow::Next,
+            Command::Halt => Flow::Halt,
+        };
+        Ok(Some(flow))
     }
 
     fn busy(&self) -> bool {
@@ -1,3 +1,6 @@
 pub mod crt;
+pub mod debugger;
+pub mod export;
 pub mod interpreter;
+pub mod ocr;
 pub mod parser;
@@ -1,7 +1,17 @@
-use std::fs::read_to_string;
+use std::{
+    env,
+    fs::{read_to_string, File},
+    io::{stdin, stdout, BufWriter},
+};
 
 use color_eyre::{eyre::eyre, eyre::Context, Result};
-use day10::{crt::Crt, interpreter::Interpreter, parser::command_list};
+use day10::{
+    crt::Crt,
+    debugger::{Debugger, RunMode, StopReason},
+    export::{save_screen, Recorder},
+    interpreter::Interpreter,
+    parser::command_list,
+};
 use log::{debug, info};
 
 fn main() -> Result<()> {
@@ -17,30 +27,74 @@ fn main() -> Result<()> {
         Err(e) => return Err(eyre!("Error while parsing commands: {}", e)),
     };
 
-    let mut interpreter = Interpreter::with_input(cmds.as_slice());
-    let mut crt = Crt::new();
+    let mut debugger = Debugger::new(Interpreter::with_input(cmds.as_slice()), Crt::new());
 
-    println!("Initial CRT Screen:\n{}", crt);
+    // `--debug [script]` starts the debugger, `--trace <file>` writes a JSON line per cycle
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(pos) = args.iter().position(|a| a == "--trace") {
+        let path = args
+            .get(pos + 1)
+            .ok_or_else(|| eyre!("--trace needs a file"))?;
+        let file = File::create(path).wrap_err_with(|| format!("Creating {}", path))?;
+        debugger.set_trace(Box::new(BufWriter::new(file)));
+    }
+    if let Some(pos) = args.iter().position(|a| a == "--debug") {
+        let mut out = stdout();
+        match args.get(pos + 1).filter(|a| !a.starts_with("--")) {
+            Some(script) => {
+                let script =
+                    read_to_string(script).wrap_err_with(|| format!("Reading {}", script))?;
+                debugger.run_script(&script, &mut out)?
+            }
+            None => debugger.run_interactive(stdin().lock(), &mut out)?,
+        }
+        return Ok(());
+    }
+
+    println!("Initial CRT Screen:\n{}", debugger.crt());
+
+    // `--png <file>` saves the final screen, `--gif <file>` an animation of the beam sweep
+    let arg_value = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|pos| args.get(pos + 1))
+    };
+    let mut recorder = arg_value("--gif").map(|_| Recorder::new(8));
 
-    let signal_cycles = vec![20, 60, 100, 140, 180, 220];
+    let signal_cycles = [20, 60, 100, 140, 180, 220];
     let mut total_strenghts = 0;
-    let mut clock: i32 = 1;
-    loop {
-        debug!("Cycle {}, X: {}", clock, interpreter.x());
+    let mut clock: i64 = 1;
+    while !debugger.interpreter().is_finished() {
+        let x = debugger.interpreter().x();
+        debug!("Cycle {}, X: {}", clock, x);
         if signal_cycles.contains(&clock) {
-            let strength = interpreter.x() * clock;
+            let strength = x * clock;
             info!("Signal Strength at Cycle {}: {}", clock, strength);
             total_strenghts += strength;
         }
-        crt.tick(interpreter.x());
-        let done = interpreter.tick();
-        if done {
-            break;
+        if let StopReason::Fault(e) = debugger.run(RunMode::Cycle)? {
+            return Err(e).wrap_err_with(|| format!("Cycle {}", clock));
+        }
+        if let Some(recorder) = &mut recorder {
+            recorder.capture(debugger.crt());
         }
         clock += 1;
     }
+    let crt = debugger.crt();
 
     println!("Combined Signal Strength: {}", total_strenghts);
     println!("Final CRT Screen:\n{}", crt);
+    match crt.read_text() {
+        Ok(text) => println!("Screen text: {}", text),
+        Err(e) => println!("Could not read the screen: {}", e),
+    }
+    if let Some(path) = arg_value("--png") {
+        save_screen(crt, path, 8).wrap_err_with(|| format!("Saving {}", path))?;
+    }
+    if let (Some(recorder), Some(path)) = (recorder, arg_value("--gif")) {
+        recorder
+            .save_gif(path, 40)
+            .wrap_err_with(|| format!("Saving {}", path))?;
+    }
     Ok(())
 }
@@ -0,0 +1,270 @@
+use thiserror::Error;
+
+/// A fixed-width bitmap font. Glyphs are drawn with `#` and `.`, one line per row
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Font {
+    pub width: usize,
+    pub height: usize,
+    /// Dark columns between two glyphs
+    pub spacing: usize,
+    pub glyphs: &'static [(char, &'static str)],
+}
+
+/// The small font used by most Advent of Code puzzles, such as 2022 day 10
+pub const FONT_4X6: Font = Font {
+    width: 4,
+    height: 6,
+    spacing: 1,
+    glyphs: &[
+        ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+        ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+        ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+        ('E', "####\n#...\n###.\n#...\n#...\n####"),
+        ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+        ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+        ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+        ('I', ".###\n..#.\n..#.\n..#.\n..#.\n.###"),
+        ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+        ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+        ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+        ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+        ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+        ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+        ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+        ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+        ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+    ],
+};
+
+/// The large font used by 2018 day 10
+pub const FONT_6X10: Font = Font {
+    width: 6,
+    height: 10,
+    spacing: 2,
+    glyphs: &[
+        (
+            'A',
+            "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+        ),
+        (
+            'B',
+            "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+        ),
+        (
+            'C',
+            ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+        ),
+        (
+            'E',
+            "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+        ),
+        (
+            'F',
+            "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+        ),
+        (
+            'G',
+            ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+        ),
+        (
+            'H',
+            "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+        ),
+        (
+            'J',
+            "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+        ),
+        (
+            'K',
+            "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+        ),
+        (
+            'L',
+            "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+        ),
+        (
+            'N',
+            "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+        ),
+        (
+            'P',
+            "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+        ),
+        (
+            'R',
+            "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+        ),
+        (
+            'X',
+            "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+        ),
+        (
+            'Z',
+            "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+        ),
+    ],
+};
+
+pub const FONTS: [Font; 2] = [FONT_4X6, FONT_6X10];
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+pub enum OcrError {
+    #[error("No font is {0} pixels tall")]
+    NoFont(usize),
+    #[error("Bitmap has {found} pixels, but {expected} are needed to match the font height")]
+    SizeMismatch { expected: usize, found: usize },
+    #[error("Unrecognized glyph at position {index}:\n{bitmap}")]
+    UnknownGlyph { index: usize, bitmap: String },
+    #[error("Lit pixel outside of any glyph in column {column}")]
+    StrayPixel { column: usize },
+}
+
+impl Font {
+    /// Find the font for a screen of the given height
+    pub fn for_height(height: usize) -> Result<Font, OcrError> {
+        FONTS
+            .into_iter()
+            .find(|f| f.height == height)
+            .ok_or(OcrError::NoFont(height))
+    }
+
+    /// Read the text in a bitmap stored row by row, `width` pixels per row.
+    ///
+    /// The bitmap has to be exactly as tall as the font. Empty glyph cells are read as spaces,
+    /// trailing ones are dropped. The spacing between glyphs and any columns after the last full
+    /// glyph have to be dark.
+    pub fn read(&self, bitmap: &[bool], width: usize) -> Result<String, OcrError> {
+        if width == 0 || bitmap.len() != width * self.height {
+            return Err(OcrError::SizeMismatch {
+                expected: width * self.height,
+                found: bitmap.len(),
+            });
+        }
+        let cell = self.width + self.spacing;
+        let count = (width + self.spacing) / cell;
+        let stray = (0..width)
+            .filter(|x| x / cell >= count || x % cell >= self.width)
+            .find(|x| (0..self.height).any(|y| bitmap[y * width + x]));
+        if let Some(column) = stray {
+            return Err(OcrError::StrayPixel { column });
+        }
+        let mut text = String::new();
+        for index in 0..count {
+            let glyph = (0..self.height)
+                .map(|y| {
+                    (0..self.width)
+                        .map(|x| match bitmap[y * width + index * cell + x] {
+                            true => '#',
+                            false => '.',
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !glyph.contains('#') {
+                text.push(' ');
+                continue;
+            }
+            match self.glyphs.iter().find(|(_, g)| *g == glyph) {
+                Some((c, _)) => text.push(*c),
+                None => {
+                    return Err(OcrError::UnknownGlyph {
+                        index,
+                        bitmap: glyph,
+                    })
+                }
+            }
+        }
+        Ok(text.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draw text with the given font, the inverse of [`Font::read`]
+    fn draw(font: &Font, text: &str) -> (Vec<bool>, usize) {
+        let cell = font.width + font.spacing;
+        let width = text.len() * cell;
+        let mut bitmap = vec![false; width * font.height];
+        for (i, c) in text.chars().enumerate() {
+            let (_, glyph) = font.glyphs.iter().find(|(g, _)| *g == c).unwrap();
+            for (y, row) in glyph.lines().enumerate() {
+                for (x, p) in row.chars().enumerate() {
+                    bitmap[y * width + i * cell + x] = p == '#';
+                }
+            }
+        }
+        (bitmap, width)
+    }
+
+    #[test]
+    fn read_both_fonts() {
+        for font in FONTS {
+            let all: String = font.glyphs.iter().map(|(c, _)| *c).collect();
+            let (bitmap, width) = draw(&font, &all);
+            assert_eq!(font.read(&bitmap, width).unwrap(), all);
+            assert_eq!(Font::for_height(font.height).unwrap(), font);
+        }
+    }
+
+    #[test]
+    fn unknown_glyph() {
+        let (mut bitmap, width) = draw(&FONT_4X6, "HI");
+        // Smudge the I
+        bitmap[width + 5 + 3] = true;
+        bitmap[5 * width + 5 + 1] = false;
+        assert_eq!(
+            FONT_4X6.read(&bitmap, width).unwrap_err(),
+            OcrError::UnknownGlyph {
+                index: 1,
+                bitmap: ".###\n..##\n..#.\n..#.\n..#.\n..##".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn stray_pixels() {
+        let (mut bitmap, width) = draw(&FONT_4X6, "HI");
+        // The spacing column between both letters
+        bitmap[2 * width + 4] = true;
+        assert_eq!(
+            FONT_4X6.read(&bitmap, width).unwrap_err(),
+            OcrError::StrayPixel { column: 4 }
+        );
+
+        // Three columns after the last letter, too few for another one
+        let (letters, width) = draw(&FONT_4X6, "HI");
+        let wider = width + 3;
+        let mut bitmap = vec![false; wider * FONT_4X6.height];
+        for (y, row) in letters.chunks(width).enumerate() {
+            bitmap[y * wider..y * wider + width].copy_from_slice(row);
+        }
+        assert_eq!(FONT_4X6.read(&bitmap, wider).unwrap(), "HI");
+        bitmap[5 * wider + width + 1] = true;
+        assert_eq!(
+            FONT_4X6.read(&bitmap, wider).unwrap_err(),
+            OcrError::StrayPixel { column: width + 1 }
+        );
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let (bitmap, width) = draw(&FONT_4X6, "HI");
+        assert_eq!(
+            FONT_6X10.read(&bitmap, width).unwrap_err(),
+            OcrError::SizeMismatch {
+                expected: 100,
+                found: 60
+            }
+        );
+        assert_eq!(
+            FONT_4X6.read(&bitmap[1..], width).unwrap_err(),
+            OcrError::SizeMismatch {
+                expected: 60,
+                found: 59
+            }
+        );
+    }
+}
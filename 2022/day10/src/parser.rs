@@ -1,24 +1,354 @@
+use std::collections::HashMap;
+
 use nom::{
-    branch::alt, bytes::complete::tag, character::complete::newline, combinator::map, multi::many1,
-    sequence::delimited, Finish, IResult,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{alpha1, alphanumeric1, char, one_of, space0, space1},
+    combinator::{eof, opt, recognize, rest},
+    multi::{many0_count, many1_count, separated_list1},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use thiserror::Error;
+
+use crate::interpreter::{
+    Arg, Instruction, InstructionSet, Opcode, Operand, OperandKind, Register,
 };
 
-use crate::interpreter::Command;
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+pub enum AssemblerError {
+    #[error("Line {line}: syntax error in `{source_line}`")]
+    Syntax { line: usize, source_line: String },
+    #[error("Line {line}: unknown instruction `{mnemonic}`")]
+    UnknownMnemonic { line: usize, mnemonic: String },
+    #[error("Line {line}: `{mnemonic}` takes {expected} operands, got {got}")]
+    OperandCount {
+        line: usize,
+        mnemonic: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Line {line}: `{operand}` is not a valid {kind:?} operand")]
+    BadOperand {
+        line: usize,
+        operand: String,
+        kind: OperandKind,
+    },
+    #[error("Line {line}: unknown label `{label}`")]
+    UnknownLabel { line: usize, label: String },
+    #[error("Line {line}: label `{label}` was already defined on line {first}")]
+    DuplicateLabel {
+        line: usize,
+        label: String,
+        first: usize,
+    },
+}
+
+/// A single line of assembly, split into its parts
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+struct SourceLine<'a> {
+    label: Option<&'a str>,
+    mnemonic: Option<&'a str>,
+    operands: Vec<&'a str>,
+}
+
+/// Assemble a program with the default instruction set
+pub fn command_list(input: &str) -> Result<Vec<Instruction>, AssemblerError> {
+    assemble(input, &InstructionSet::default())
+}
+
+/// Assemble a program, one instruction per line.
+///
+/// Each line may start with a `label:`, and `#` or `;` start a comment. Operands are separated by
+/// spaces or commas. Jump targets are either labels or offsets relative to the jumping instruction,
+/// such as `+2` or `-3`. The sign of a forward jump is optional, so `2` is the same as `+2`, as
+/// in assembunny. Labels can't start with a digit, so offsets are never mistaken for them.
+pub fn assemble(input: &str, set: &InstructionSet) -> Result<Vec<Instruction>, AssemblerError> {
+    // First pass: split lines and find the instruction index for every label
+    let mut lines = Vec::new();
+    let mut labels: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, l) in input.lines().enumerate() {
+        let line_no = i + 1;
+        let (_, parsed) = source_line(l).map_err(|_| AssemblerError::Syntax {
+            line: line_no,
+            source_line: l.to_string(),
+        })?;
+        if let Some(label) = parsed.label {
+            if let Some((_, first)) = labels.insert(label, (lines.len(), line_no)) {
+                return Err(AssemblerError::DuplicateLabel {
+                    line: line_no,
+                    label: label.to_string(),
+                    first,
+                });
+            }
+        }
+        if parsed.mnemonic.is_some() {
+            lines.push((line_no, parsed));
+        }
+    }
 
-pub fn command_list(input: &str) -> Result<Vec<Command>, nom::error::Error<&str>> {
-    many1(command)(input).finish().map(|(_, r)| r)
+    // Second pass: resolve operands and build the instructions
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, (line, parsed))| {
+            let mnemonic = parsed.mnemonic.unwrap_or_default();
+            let opcode = set
+                .get(mnemonic)
+                .ok_or_else(|| AssemblerError::UnknownMnemonic {
+                    line: *line,
+                    mnemonic: mnemonic.to_string(),
+                })?;
+            let args = resolve_operands(opcode, &parsed.operands, index, *line, &labels)?;
+            Ok(Instruction {
+                cmd: (opcode.build)(&args),
+                cycles: opcode.cycles,
+            })
+        })
+        .collect()
 }
 
-fn command(input: &str) -> IResult<&str, Command> {
-    alt((noop, addx))(input)
+fn resolve_operands(
+    opcode: &Opcode,
+    operands: &[&str],
+    index: usize,
+    line: usize,
+    labels: &HashMap<&str, (usize, usize)>,
+) -> Result<Vec<Arg>, AssemblerError> {
+    if operands.len() != opcode.operands.len() {
+        return Err(AssemblerError::OperandCount {
+            line,
+            mnemonic: opcode.mnemonic.to_string(),
+            expected: opcode.operands.len(),
+            got: operands.len(),
+        });
+    }
+    opcode
+        .operands
+        .iter()
+        .zip(operands)
+        .map(|(kind, operand)| {
+            let bad_operand = || AssemblerError::BadOperand {
+                line,
+                operand: operand.to_string(),
+                kind: *kind,
+            };
+            match kind {
+                OperandKind::Register => Register::from_name(operand)
+                    .map(Arg::Register)
+                    .ok_or_else(bad_operand),
+                OperandKind::Value => match operand.parse::<i64>() {
+                    Ok(v) => Ok(Arg::Value(Operand::Immediate(v))),
+                    Err(_) => Register::from_name(operand)
+                        .map(|r| Arg::Value(Operand::Register(r)))
+                        .ok_or_else(bad_operand),
+                },
+                OperandKind::Target => match operand.parse::<isize>() {
+                    Ok(offset) => Ok(Arg::Target(index as isize + offset)),
+                    Err(_) => labels
+                        .get(operand)
+                        .map(|(target, _)| Arg::Target(*target as isize))
+                        .ok_or_else(|| AssemblerError::UnknownLabel {
+                            line,
+                            label: operand.to_string(),
+                        }),
+                },
+            }
+        })
+        .collect()
 }
 
-fn noop(input: &str) -> IResult<&str, Command> {
-    let p = tag("noop\n");
-    map(p, |_| Command::Noop)(input)
+fn identifier(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0_count(alt((alphanumeric1, tag("_")))),
+    ))(input)
 }
 
-fn addx(input: &str) -> IResult<&str, Command> {
-    let p = delimited(tag("addx "), nom::character::complete::i32, newline);
-    map(p, Command::Addx)(input)
+fn operand(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        opt(one_of("+-")),
+        many1_count(alt((alphanumeric1, tag("_")))),
+    ))(input)
+}
+
+fn operand_separator(input: &str) -> IResult<&str, &str> {
+    alt((delimited(space0, tag(","), space0), space1))(input)
+}
+
+fn comment(input: &str) -> IResult<&str, &str> {
+    preceded(one_of("#;"), rest)(input)
+}
+
+fn source_line(input: &str) -> IResult<&str, SourceLine<'_>> {
+    let instruction = pair(
+        identifier,
+        opt(preceded(
+            space1,
+            separated_list1(operand_separator, operand),
+        )),
+    );
+    let mut p = tuple((
+        space0,
+        opt(terminated(identifier, pair(char(':'), space0))),
+        opt(instruction),
+        space0,
+        opt(comment),
+        eof,
+    ));
+    let (rest, (_, label, instruction, _, _, _)) = p(input)?;
+    let (mnemonic, operands) = match instruction {
+        Some((m, ops)) => (Some(m), ops.unwrap_or_default()),
+        None => (None, vec![]),
+    };
+    Ok((
+        rest,
+        SourceLine {
+            label,
+            mnemonic,
+            operands,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Command, Interpreter, InterpreterError};
+
+    #[test]
+    fn day10_example() {
+        let program = command_list("noop\naddx 3\naddx -5\n").unwrap();
+        let mut interpreter = Interpreter::with_input(&program);
+        assert_eq!(interpreter.run().unwrap(), 5);
+        assert_eq!(interpreter.x(), -1);
+    }
+
+    #[test]
+    fn labels_and_comments() {
+        let source = "\
+            ; factorial of 5 into x
+            set x 1
+            set n 5
+        loop:
+            mul x, n    # x *= n
+            add n -1
+            jnz n loop
+            halt
+            addx 100
+        ";
+        let program = command_list(source).unwrap();
+        assert_eq!(
+            program[4].cmd,
+            Command::JumpIfNotZero(Operand::Register(Register('n')), 2)
+        );
+        let mut interpreter = Interpreter::with_input(&program);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.x(), 120);
+    }
+
+    #[test]
+    fn overflow_halts_with_error() {
+        let program = command_list("set x 2\nloop:\nmul x x\njmp loop").unwrap();
+        let mut interpreter = Interpreter::with_input(&program);
+        assert_eq!(
+            interpreter.run(),
+            Err(InterpreterError::Overflow {
+                pc: 1,
+                cmd: Command::Mul(Register::X, Operand::Register(Register::X)),
+                register: Register::X
+            })
+        );
+        assert!(interpreter.is_finished());
+        assert_eq!(interpreter.x(), 1 << 32);
+    }
+
+    #[test]
+    fn custom_opcodes() {
+        // The assembunny example from 2016 day 12, using relative jumps
+        let mut set = InstructionSet::default();
+        for opcode in [
+            Opcode {
+                mnemonic: "cpy",
+                operands: &[OperandKind::Value, OperandKind::Register],
+                cycles: 1,
+                build: |a| Command::Set(a[1].register(), a[0].value()),
+            },
+            Opcode {
+                mnemonic: "inc",
+                operands: &[OperandKind::Register],
+                cycles: 1,
+                build: |a| Command::Add(a[0].register(), Operand::Immediate(1)),
+            },
+            Opcode {
+                mnemonic: "dec",
+                operands: &[OperandKind::Register],
+                cycles: 1,
+                build: |a| Command::Add(a[0].register(), Operand::Immediate(-1)),
+            },
+        ] {
+            set.insert(opcode);
+        }
+        let program = assemble("cpy 41 a\ninc a\ninc a\ndec a\njnz a 2\ndec a", &set).unwrap();
+        let mut interpreter = Interpreter::with_input(&program);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.register(Register('a')), 42);
+    }
+
+    #[test]
+    fn relative_jumps() {
+        let program = command_list("noop\njmp 2\njmp +2\njmp -1\njmp -3").unwrap();
+        let targets: Vec<_> = program.iter().skip(1).map(|i| &i.cmd).collect();
+        assert_eq!(
+            targets,
+            [
+                &Command::Jump(3),
+                &Command::Jump(4),
+                &Command::Jump(2),
+                &Command::Jump(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn assembler_errors() {
+        assert_eq!(
+            command_list("noop\nfoo 1").unwrap_err(),
+            AssemblerError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            command_list("jmp nowhere").unwrap_err(),
+            AssemblerError::UnknownLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            }
+        );
+        assert!(matches!(
+            command_list("a:\na: noop").unwrap_err(),
+            AssemblerError::DuplicateLabel {
+                line: 2,
+                first: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            command_list("addx 1 2").unwrap_err(),
+            AssemblerError::OperandCount {
+                expected: 1,
+                got: 2,
+                ..
+            }
+        ));
+        assert!(matches!(
+            command_list("add 1 2").unwrap_err(),
+            AssemblerError::BadOperand { .. }
+        ));
+        assert!(matches!(
+            command_list("addx 1 !").unwrap_err(),
+            AssemblerError::Syntax { line: 1, .. }
+        ));
+    }
 }
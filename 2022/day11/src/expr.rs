@@ -0,0 +1,133 @@
+use std::fmt::Display;
+
+use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub};
+use thiserror::Error;
+
+use crate::monkey::ItemWorryLevel;
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Error)]
+pub enum ExprError {
+    #[error("`{0}` overflows")]
+    Overflow(String),
+    #[error("`{0}` is negative")]
+    Negative(String),
+    #[error("`{0}` divides by zero")]
+    DivisionByZero(String),
+}
+
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+impl BinOp {
+    fn symbol(&self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+        }
+    }
+}
+
+/// The right hand side of a monkey's operation, such as `old * (old + 3)`
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub enum Expr {
+    Old,
+    Const(ItemWorryLevel),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
+impl Expr {
+    pub fn binary(lhs: Expr, op: BinOp, rhs: Expr) -> Self {
+        Expr::Binary(Box::new(lhs), op, Box::new(rhs))
+    }
+
+    /// Evaluate the expression with `old` set to the given worry level.
+    ///
+    /// Worry levels can't be negative, and divisions round down
+    pub fn eval(&self, old: &ItemWorryLevel) -> Result<ItemWorryLevel, ExprError> {
+        match self {
+            Expr::Old => Ok(old.to_owned()),
+            Expr::Const(c) => Ok(c.to_owned()),
+            Expr::Binary(lhs, op, rhs) => {
+                let (l, r) = (lhs.eval(old)?, rhs.eval(old)?);
+                let result = match op {
+                    BinOp::Add => CheckedAdd::checked_add(&l, &r),
+                    BinOp::Sub => CheckedSub::checked_sub(&l, &r),
+                    BinOp::Mul => CheckedMul::checked_mul(&l, &r),
+                    BinOp::Div => CheckedDiv::checked_div(&l, &r),
+                };
+                result.ok_or_else(|| {
+                    let e = self.to_string();
+                    match op {
+                        BinOp::Sub => ExprError::Negative(e),
+                        BinOp::Div => ExprError::DivisionByZero(e),
+                        _ => ExprError::Overflow(e),
+                    }
+                })
+            }
+        }
+    }
+}
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Const(c) => write!(f, "{}", c),
+            Expr::Binary(lhs, op, rhs) => {
+                // Parenthesize nested operations instead of tracking precedence
+                let write_operand = |f: &mut std::fmt::Formatter<'_>, e: &Expr| match e {
+                    Expr::Binary(..) => write!(f, "({})", e),
+                    _ => write!(f, "{}", e),
+                };
+                write_operand(f, lhs)?;
+                write!(f, " {} ", op.symbol())?;
+                write_operand(f, rhs)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::expr;
+
+    fn parse(input: &str) -> Expr {
+        let (rest, e) = expr(input).unwrap();
+        assert_eq!(rest, "");
+        e
+    }
+
+    fn worry(v: u32) -> ItemWorryLevel {
+        ItemWorryLevel::from(v)
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(parse("old * old").eval(&worry(7)), Ok(worry(49)));
+        assert_eq!(parse("2 + old * 3").eval(&worry(4)), Ok(worry(14)));
+        assert_eq!(parse("(2 + old) * 3").eval(&worry(4)), Ok(worry(18)));
+        assert_eq!(parse("old - 10 - 3").eval(&worry(20)), Ok(worry(7)));
+        assert_eq!(parse("old / (old - 5)").eval(&worry(10)), Ok(worry(2)));
+        assert_eq!(
+            parse("(old+1)*(old-1)").to_string(),
+            "(old + 1) * (old - 1)"
+        );
+    }
+
+    #[test]
+    fn eval_errors() {
+        assert!(matches!(
+            parse("3 - old").eval(&worry(4)),
+            Err(ExprError::Negative(_))
+        ));
+        assert!(matches!(
+            parse("1 / (old - 4)").eval(&worry(4)),
+            Err(ExprError::DivisionByZero(_))
+        ));
+    }
+}
@@ -1,2 +1,5 @@
+pub mod expr;
 pub mod monkey;
 pub mod parser;
+pub mod reducer;
+pub mod troop;
@@ -1,11 +1,12 @@
 use std::fs::read_to_string;
 
-use color_eyre::{
-    eyre::{eyre, Context},
-    Result,
+use color_eyre::{eyre::Context, Result};
+use day11::{
+    monkey::ItemWorryLevel,
+    parser::parse_monkeys,
+    reducer::{DivideBy, ModuloLcm},
+    troop::Troop,
 };
-use day11::{monkey::Monkey, parser::monkeys};
-use itertools::Itertools;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
@@ -13,66 +14,38 @@ fn main() -> Result<()> {
 
     let input = read_to_string("input.txt").wrap_err("Reading input.txt")?;
 
-    let (_, mut monkeys) = match monkeys(&input) {
-        Ok(m) => m,
-        Err(e) => return Err(eyre!("Error while reading monkeys: {}", e)),
-    };
+    let monkeys = parse_monkeys(&input).wrap_err("Reading monkeys")?;
 
-    let mut panic_monkeys = monkeys.clone();
+    let mut troop = Troop::new(monkeys.clone(), DivideBy::new(ItemWorryLevel::from(3u8))?);
+    let mut panic_troop = Troop::new(monkeys.clone(), ModuloLcm::for_monkeys(&monkeys)?);
 
     for i in 0..20 {
         println!("\nRound {}", i);
-        for m in &monkeys {
+        for m in troop.monkeys() {
             println!("  {}", m);
         }
-        monkey_business(&mut monkeys, false)?;
+        troop.run_round().wrap_err("Error during Monkey Business")?;
     }
 
-    for m in &monkeys {
+    for m in troop.monkeys() {
         println!(
             "Monkey {} inspected items {} times",
             m.id(),
             m.inspect_count()
         );
     }
-    let top_business = monkeys
-        .iter()
-        .map(|m| m.inspect_count())
-        .sorted()
-        .rev()
-        .take(2)
-        .collect_vec();
     println!(
         "Monkey business level after 20 rounds (non-panic): {}",
-        top_business[0] * top_business[1]
+        troop.monkey_business()
     );
 
-    for i in 0..10000 {
-        print!("\rRound {}/9999", i);
-        monkey_business(&mut panic_monkeys, true)?;
-    }
-    println!();
-    let top_business = panic_monkeys
-        .iter()
-        .map(|m| m.inspect_count())
-        .sorted()
-        .rev()
-        .take(2)
-        .collect_vec();
+    panic_troop
+        .run_with_cycle_detection(10000)
+        .wrap_err("Error during Monkey Business")?;
     println!(
-        "Monkey business level after 1000 rounds (PANIC): {}",
-        top_business[0] as u64 * top_business[1] as u64
+        "Monkey business level after 10000 rounds (PANIC): {}",
+        panic_troop.monkey_business()
     );
 
     Ok(())
 }
-
-fn monkey_business(monkeys: &mut [Monkey], panic: bool) -> Result<()> {
-    for i in 0..monkeys.len() {
-        let mut m = monkeys.get(i).unwrap().clone();
-        m.monkey_business(monkeys, panic)
-            .map_err(|e| eyre!("Error during Monkey Business: {:?}", e))?;
-        let _ = std::mem::replace(&mut monkeys[i], m);
-    }
-    Ok(())
-}
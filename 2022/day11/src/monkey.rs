@@ -1,152 +1,160 @@
-use std::fmt::Display;
+use std::{collections::VecDeque, fmt::Display};
 
-use itertools::Itertools;
 use log::info;
+use num_traits::Zero;
+use thiserror::Error;
 
+use crate::{
+    expr::{Expr, ExprError},
+    reducer::WorryReducer,
+};
+
+/// Worry levels grow quickly, enable the `bigint` feature to never overflow
+#[cfg(not(feature = "bigint"))]
 pub type ItemWorryLevel = u128;
+#[cfg(feature = "bigint")]
+pub type ItemWorryLevel = num_bigint::BigUint;
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Error)]
 pub enum MonkeyError {
-    ItemNotFound,
-    MonkeyNotFound,
-}
-
-#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
-pub enum InspectValue {
-    Input,
-    Fixed(u32),
+    #[error("Monkey {0} does not exist")]
+    MonkeyNotFound(usize),
+    #[error("Operation failed: {0}")]
+    Operation(#[from] ExprError),
 }
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
-pub enum InspectOp {
-    Add,
-    Mul,
-    Sub,
-    Div,
+/// An item held by a monkey. The ID stays the same while the item is thrown around
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct Item {
+    pub id: usize,
+    pub worry: ItemWorryLevel,
 }
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
 pub struct Monkey {
     id: usize,
-    items: Vec<ItemWorryLevel>,
-    inspect_op: InspectOp,
-    inspect_value: InspectValue,
+    items: VecDeque<Item>,
+    operation: Expr,
     worrytest_value: ItemWorryLevel,
     target_worried: usize,
     target_unworried: usize,
-    inspected_items: u32,
+    inspected_items: u64,
 }
 impl Monkey {
+    /// Create a monkey. Items are numbered in the order given here, see [`Monkey::number_items`]
     pub fn new(
         id: usize,
-        items: Vec<u32>,
-        inspect_op: InspectOp,
-        inspect_value: InspectValue,
-        worrytest_value: u32,
+        items: Vec<ItemWorryLevel>,
+        operation: Expr,
+        worrytest_value: ItemWorryLevel,
         target_worried: usize,
         target_unworried: usize,
     ) -> Self {
         Monkey {
             id,
-            items: items.into_iter().map(|v| v.into()).collect_vec(),
-            inspect_op,
-            inspect_value,
-            worrytest_value: worrytest_value as ItemWorryLevel,
+            items: items
+                .into_iter()
+                .enumerate()
+                .map(|(id, worry)| Item { id, worry })
+                .collect(),
+            operation,
+            worrytest_value,
             target_worried,
             target_unworried,
             inspected_items: 0,
         }
     }
 
-    pub fn monkey_business(
-        &mut self,
-        monkeys: &mut [Monkey],
-        panic_mode: bool,
-    ) -> Result<(), MonkeyError> {
-        for _ in 0..self.items.len() {
-            let item = self.items.remove(0);
-            let worried_item = if panic_mode {
-                // Modulo trick, had to look this one up. Only works because the div factors are all prime here.
-                let w = self.inspect(&item)
-                    % monkeys
-                        .iter()
-                        .map(|m| m.worrytest_value)
-                        .product::<ItemWorryLevel>();
-                info!("Monkey {} is done inspecting item {}, still panicking but modulo tricking to {}", self.id, item, w);
-                w
-            } else {
-                let w = self.inspect(&item) / 3;
-                info!(
-                    "Monkey {} is done inspecting item {}, worry dropped to {}",
-                    self.id, item, w
-                );
-                w
-            };
-
-            self.throw_item(worried_item, monkeys)?;
+    /// Give the items held by this monkey new IDs, starting at `next_id`
+    pub fn number_items(&mut self, next_id: &mut usize) {
+        for item in &mut self.items {
+            item.id = *next_id;
+            *next_id += 1;
         }
-        Ok(())
     }
 
-    fn inspect(&mut self, item: &ItemWorryLevel) -> ItemWorryLevel {
-        self.inspected_items += 1;
-        let factor = match self.inspect_value {
-            InspectValue::Input => *item,
-            InspectValue::Fixed(f) => f as ItemWorryLevel,
-        };
-
-        let worry = match self.inspect_op {
-            InspectOp::Add => item + factor,
-            InspectOp::Mul => item * factor,
-            InspectOp::Sub => item - factor,
-            InspectOp::Div => item / factor,
+    /// Inspect the first item and decide where to throw it.
+    ///
+    /// Returns the target monkey and the item with its new worry level, or `None` if the monkey
+    /// has no items left. If the operation fails, the item stays with the monkey and doesn't
+    /// count as inspected
+    pub fn inspect_next(
+        &mut self,
+        reducer: &dyn WorryReducer,
+    ) -> Option<Result<(usize, Item), MonkeyError>> {
+        let item = self.items.front()?;
+        let (worry, target) = match self.process(&item.worry, reducer) {
+            Ok(processed) => processed,
+            Err(e) => return Some(Err(e)),
         };
+        let id = item.id;
+        self.items.pop_front();
+        self.inspected_items += 1;
         info!(
-            "Monkey {} inspects item {}, worry rises to {}",
-            self.id, item, worry
+            "Monkey {} throws item {} with worry {} to monkey {}",
+            self.id, id, worry, target
         );
-        worry
+        Some(Ok((target, Item { id, worry })))
     }
 
-    fn throw_item(
-        &mut self,
-        item: ItemWorryLevel,
-        monkeys: &mut [Monkey],
-    ) -> Result<(), MonkeyError> {
-        let target = match self.is_worried_enough(&item) {
-            true => {
-                info!(
-                    "Monkey {} thinks you are worried, throws item {} to monkey {}",
-                    self.id, item, self.target_worried
-                );
-                self.target_worried
-            }
-            false => {
-                info!(
-                    "Monkey {} thinks you are cool, throws item {} to monkey {}",
-                    self.id, item, self.target_unworried
-                );
-                self.target_unworried
-            }
-        };
-        let reciever = monkeys.get_mut(target).ok_or(MonkeyError::MonkeyNotFound)?;
-        reciever.take_item(item);
-        Ok(())
+    /// What happens to an item with the given worry level, without modifying the monkey.
+    ///
+    /// Returns the new worry level and the monkey the item is thrown to
+    pub fn process(
+        &self,
+        worry: &ItemWorryLevel,
+        reducer: &dyn WorryReducer,
+    ) -> Result<(ItemWorryLevel, usize), MonkeyError> {
+        let worry = reducer.reduce(self.operation.eval(worry)?);
+        let target = self.target_for(&worry);
+        Ok((worry, target))
+    }
+
+    /// The monkey an item with the given (already updated) worry level is thrown to
+    fn target_for(&self, worry: &ItemWorryLevel) -> usize {
+        if (worry % self.test_divisor()).is_zero() {
+            self.target_worried
+        } else {
+            self.target_unworried
+        }
+    }
+
+    pub fn take_item(&mut self, item: Item) {
+        info!("Monkey {} received item {}", self.id, item.id);
+        self.items.push_back(item);
+    }
+
+    pub fn items(&self) -> &VecDeque<Item> {
+        &self.items
+    }
+
+    /// Remove all items, such as to place them again after jumping ahead
+    pub fn clear_items(&mut self) {
+        self.items.clear()
+    }
+
+    pub fn operation(&self) -> &Expr {
+        &self.operation
     }
 
-    pub fn take_item(&mut self, item: ItemWorryLevel) {
-        info!("Monkey {} received a new item! {}", self.id, &item);
-        self.items.push(item);
+    /// The divisor used to decide where to throw an item
+    pub fn test_divisor(&self) -> &ItemWorryLevel {
+        &self.worrytest_value
     }
 
-    fn is_worried_enough(&self, worry: &ItemWorryLevel) -> bool {
-        (worry % self.worrytest_value) == 0
+    /// Both monkeys this one can throw to
+    pub fn targets(&self) -> [usize; 2] {
+        [self.target_worried, self.target_unworried]
     }
 
-    pub fn inspect_count(&self) -> u32 {
+    pub fn inspect_count(&self) -> u64 {
         self.inspected_items
     }
 
+    pub fn add_inspections(&mut self, count: u64) {
+        self.inspected_items += count;
+    }
+
     pub fn id(&self) -> usize {
         self.id
     }
@@ -157,11 +165,46 @@ impl Display for Monkey {
         write!(f, "Monkey {}: ", self.id)?;
         for (i, it) in self.items.iter().enumerate() {
             if i + 1 == self.items.len() {
-                write!(f, "{}", it)?;
+                write!(f, "{}", it.worry)?;
             } else {
-                write!(f, "{},", it)?;
+                write!(f, "{},", it.worry)?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::BinOp, reducer::NoReduction};
+
+    #[test]
+    fn failed_inspection_keeps_the_item() {
+        let operation = Expr::binary(
+            Expr::Old,
+            BinOp::Sub,
+            Expr::Const(ItemWorryLevel::from(10u8)),
+        );
+        let mut monkey = Monkey::new(
+            0,
+            vec![ItemWorryLevel::from(5u8)],
+            operation,
+            ItemWorryLevel::from(2u8),
+            1,
+            1,
+        );
+        assert!(matches!(
+            monkey.inspect_next(&NoReduction),
+            Some(Err(MonkeyError::Operation(ExprError::Negative(_))))
+        ));
+        assert_eq!(monkey.inspect_count(), 0);
+        assert_eq!(
+            monkey.items(),
+            &[Item {
+                id: 0,
+                worry: ItemWorryLevel::from(5u8)
+            }]
+        );
+    }
+}
@@ -1,24 +1,58 @@
-use itertools::Itertools;
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take};
-use nom::character::complete::{newline, u32 as nom32};
-use nom::combinator::{eof, map};
-use nom::multi::{many0, many_till, separated_list0};
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit1, newline, one_of, u32 as nom32};
+use nom::combinator::{eof, map, map_res, value, verify};
+use nom::error::{context, VerboseError, VerboseErrorKind};
+use nom::multi::{fold_many0, many0, many_till, separated_list0};
 use nom::sequence::tuple;
 use nom::{
     character::complete::{space0, space1},
-    sequence::{delimited, pair, separated_pair},
-    IResult,
+    sequence::{delimited, pair},
+    Finish, IResult,
 };
+use num_traits::Zero;
+use thiserror::Error;
 
-use crate::monkey::{InspectOp, InspectValue, ItemWorryLevel, Monkey};
+use crate::expr::{BinOp, Expr};
+use crate::monkey::{ItemWorryLevel, Monkey};
 
-pub fn monkeys(input: &str) -> IResult<&str, Vec<Monkey>> {
-    let p = many_till(monkey, eof);
-    map(p, |a| a.0)(input)
+type Res<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+#[error("Line {line}: invalid {context}: `{found}`")]
+pub struct ParseError {
+    pub line: usize,
+    pub context: &'static str,
+    pub found: String,
+}
+
+/// Parse all monkeys in the input
+pub fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    monkeys(input).finish().map(|(_, m)| m).map_err(|e| {
+        // Report the innermost context, as that is closest to the actual problem
+        let (rest, context) = e
+            .errors
+            .iter()
+            .find_map(|(rest, kind)| match kind {
+                VerboseErrorKind::Context(c) => Some((*rest, *c)),
+                _ => None,
+            })
+            .unwrap_or((e.errors[0].0, "monkey"));
+        let offset = input.len() - rest.len();
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        ParseError {
+            line: input[..offset].matches('\n').count() + 1,
+            context,
+            found: input[line_start..].lines().next().unwrap_or("").to_string(),
+        }
+    })
+}
+
+pub fn monkeys(input: &str) -> Res<'_, Vec<Monkey>> {
+    map(many_till(monkey, eof), |(m, _)| m)(input)
 }
 
-fn monkey(input: &str) -> IResult<&str, Monkey> {
+fn monkey(input: &str) -> Res<'_, Monkey> {
     let p = tuple((
         monkey_header,
         monkey_items,
@@ -30,20 +64,11 @@ fn monkey(input: &str) -> IResult<&str, Monkey> {
     ));
     map(
         p,
-        |(
-            id,
-            items,
-            (inspect_op, inspect_value),
-            worrytest_value,
-            target_worried,
-            target_unworried,
-            _,
-        )| {
+        |(id, items, operation, worrytest_value, target_worried, target_unworried, _)| {
             Monkey::new(
                 id,
                 items,
-                inspect_op,
-                inspect_value,
+                operation,
                 worrytest_value,
                 target_worried,
                 target_unworried,
@@ -52,79 +77,137 @@ fn monkey(input: &str) -> IResult<&str, Monkey> {
     )(input)
 }
 
-fn monkey_header(input: &str) -> IResult<&str, usize> {
+fn monkey_header(input: &str) -> Res<'_, usize> {
     let p = delimited(tag("Monkey "), nom32, tag(":\n"));
-    map(p, |id| id as usize)(input)
+    context("monkey header", map(p, |id| id as usize))(input)
 }
 
-fn monkey_items(input: &str) -> IResult<&str, Vec<u32>> {
-    fn _single(input: &str) -> IResult<&str, Vec<u32>> {
-        let p = delimited(pair(space1, tag("Starting items: ")), nom32, newline);
-        map(p, |single| vec![single])(input)
-    }
-
-    fn _list(input: &str) -> IResult<&str, Vec<u32>> {
-        delimited(
-            pair(space1, tag("Starting items: ")),
-            separated_list0(tag(", "), nom32),
-            newline,
-        )(input)
-    }
-
-    alt((_single, _list))(input)
-}
-
-fn monkey_op(input: &str) -> IResult<&str, (InspectOp, InspectValue)> {
-    let p = delimited::<_, _, _, _, nom::error::Error<&str>, _, _, _>(
-        pair(space1, tag("Operation: new = old ")),
-        separated_pair(take(1_u8), space0, monkey_op_value),
+fn monkey_items(input: &str) -> Res<'_, Vec<ItemWorryLevel>> {
+    let p = delimited(
+        pair(space1, tag("Starting items: ")),
+        separated_list0(tag(", "), worry),
         newline,
     );
-
-    map(p, |(op, val)| match op {
-        "-" => (InspectOp::Sub, val),
-        "+" => (InspectOp::Add, val),
-        "*" => (InspectOp::Mul, val),
-        "/" => (InspectOp::Div, val),
-        _ => panic!(),
-    })(input)
+    context("starting items", p)(input)
 }
 
-fn monkey_op_value(input: &str) -> IResult<&str, InspectValue> {
-    fn _numeric(input: &str) -> IResult<&str, InspectValue> {
-        map(nom32, InspectValue::Fixed)(input)
-    }
-
-    fn _old(input: &str) -> IResult<&str, InspectValue> {
-        map(tag("old"), |_| InspectValue::Input)(input)
-    }
-
-    alt((_numeric, _old))(input)
+fn monkey_op(input: &str) -> Res<'_, Expr> {
+    let p = delimited(
+        tuple((space1, tag("Operation: new"), space0, char('='), space0)),
+        expr,
+        pair(space0, newline),
+    );
+    context("operation", p)(input)
 }
 
-fn monkey_test(input: &str) -> IResult<&str, u32> {
-    delimited::<_, _, _, _, nom::error::Error<&str>, _, _, _>(
-        pair(space1, tag("Test: divisible by ")),
-        nom32,
-        newline,
-    )(input)
+fn monkey_test(input: &str) -> Res<'_, ItemWorryLevel> {
+    // Every worry level would be divisible by 0, and testing it panics
+    let divisor = verify(worry, |d: &ItemWorryLevel| !d.is_zero());
+    let p = delimited(pair(space1, tag("Test: divisible by ")), divisor, newline);
+    context("test", p)(input)
 }
 
-fn monkey_worried_target(input: &str) -> IResult<&str, usize> {
-    let p = delimited::<_, _, _, _, nom::error::Error<&str>, _, _, _>(
+fn monkey_worried_target(input: &str) -> Res<'_, usize> {
+    let p = delimited(
         pair(space1, tag("If true: throw to monkey ")),
         nom32,
         newline,
     );
-
-    map(p, |target| target as usize)(input)
+    context("true target", map(p, |target| target as usize))(input)
 }
 
-fn monkey_unworried_target(input: &str) -> IResult<&str, usize> {
-    let p = delimited::<_, _, _, _, nom::error::Error<&str>, _, _, _>(
+fn monkey_unworried_target(input: &str) -> Res<'_, usize> {
+    let p = delimited(
         pair(space1, tag("If false: throw to monkey ")),
         nom32,
         newline,
     );
-    map(p, |target| target as usize)(input)
+    context("false target", map(p, |target| target as usize))(input)
+}
+
+fn worry(input: &str) -> Res<'_, ItemWorryLevel> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// Parse an arithmetic expression with `old`, constants, `+ - * /` and parentheses.
+///
+/// `*` and `/` bind stronger than `+` and `-`, all operators are left-associative
+pub fn expr(input: &str) -> Res<'_, Expr> {
+    binary_chain(input, term, "+-")
+}
+
+fn term(input: &str) -> Res<'_, Expr> {
+    binary_chain(input, factor, "*/")
+}
+
+fn factor(input: &str) -> Res<'_, Expr> {
+    alt((
+        value(Expr::Old, tag("old")),
+        map(worry, Expr::Const),
+        delimited(pair(char('('), space0), expr, pair(space0, char(')'))),
+    ))(input)
+}
+
+/// Parse `operand (op operand)*` and fold it into a left-associative tree
+fn binary_chain<'a>(
+    input: &'a str,
+    operand: fn(&'a str) -> Res<'a, Expr>,
+    ops: &'static str,
+) -> Res<'a, Expr> {
+    let (input, first) = operand(input)?;
+    fold_many0(
+        pair(delimited(space0, one_of(ops), space0), operand),
+        move || first.clone(),
+        |lhs, (op, rhs)| {
+            let op = match op {
+                '+' => BinOp::Add,
+                '-' => BinOp::Sub,
+                '*' => BinOp::Mul,
+                _ => BinOp::Div,
+            };
+            Expr::binary(lhs, op, rhs)
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::sequence::{preceded, terminated};
+
+    use super::*;
+
+    #[test]
+    fn parse_errors() {
+        let second_monkey = "Monkey 0:\n  Starting items: 1\n  Operation: new = old\n  \
+            Test: divisible by 2\n    If true: throw to monkey 1\n    If false: throw to monkey 1\n\n\
+            Monkey 1:\n  Starting items: x\n";
+        assert_eq!(parse_monkeys(second_monkey).unwrap_err().line, 9);
+        let input = "Monkey 0:\n  Starting items: 79, 98\n  Operation: new = old % 19\n";
+        assert_eq!(
+            parse_monkeys(input).unwrap_err(),
+            ParseError {
+                line: 3,
+                context: "operation",
+                found: "  Operation: new = old % 19".to_string()
+            }
+        );
+        let input = "Monkey 0:\n  Starting items: 1\n  Operation: new = old\n  \
+            Test: divisible by 0\n    If true: throw to monkey 0\n    If false: throw to monkey 0\n";
+        assert_eq!(
+            parse_monkeys(input).unwrap_err(),
+            ParseError {
+                line: 4,
+                context: "test",
+                found: "  Test: divisible by 0".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn terminated_expressions() {
+        let (rest, e) = terminated(expr, newline)("old * (old + 2)\n").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(e.to_string(), "old * (old + 2)");
+        assert!(preceded(tag("new = "), expr)("new = ()").is_err());
+    }
 }
@@ -0,0 +1,176 @@
+use num_traits::Zero;
+use thiserror::Error;
+
+use crate::{
+    expr::{BinOp, Expr},
+    monkey::{ItemWorryLevel, Monkey},
+};
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Error)]
+pub enum ReducerError {
+    #[error("Monkey {monkey} uses `{operation}`, which can't be reduced modulo the divisors")]
+    UnsupportedOperation { monkey: usize, operation: String },
+    #[error("Monkey {0} tests for divisibility by 0")]
+    ZeroTestDivisor(usize),
+    #[error("Worry levels can't be divided by 0")]
+    DivisionByZero,
+}
+
+/// Keeps worry levels manageable after a monkey inspected an item
+pub trait WorryReducer {
+    fn reduce(&self, worry: ItemWorryLevel) -> ItemWorryLevel;
+}
+
+/// Relief that the item wasn't damaged divides the worry level by a constant
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct DivideBy(ItemWorryLevel);
+impl DivideBy {
+    pub fn new(divisor: ItemWorryLevel) -> Result<Self, ReducerError> {
+        if divisor.is_zero() {
+            return Err(ReducerError::DivisionByZero);
+        }
+        Ok(DivideBy(divisor))
+    }
+
+    pub fn divisor(&self) -> &ItemWorryLevel {
+        &self.0
+    }
+}
+impl WorryReducer for DivideBy {
+    fn reduce(&self, worry: ItemWorryLevel) -> ItemWorryLevel {
+        let DivideBy(divisor) = self;
+        worry / divisor
+    }
+}
+
+/// Keep worry levels modulo the least common multiple of all test divisors.
+///
+/// This doesn't change where any monkey throws an item, as long as the operations only add and
+/// multiply. It works for any divisors, prime or not.
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct ModuloLcm(ItemWorryLevel);
+impl ModuloLcm {
+    /// Fails if any monkey subtracts or divides, as that doesn't survive the reduction, or tests
+    /// for divisibility by 0
+    pub fn for_monkeys(monkeys: &[Monkey]) -> Result<Self, ReducerError> {
+        if let Some(m) = monkeys.iter().find(|m| m.test_divisor().is_zero()) {
+            return Err(ReducerError::ZeroTestDivisor(m.id()));
+        }
+        if let Some(m) = monkeys
+            .iter()
+            .find(|m| !only_adds_and_multiplies(m.operation()))
+        {
+            return Err(ReducerError::UnsupportedOperation {
+                monkey: m.id(),
+                operation: m.operation().to_string(),
+            });
+        }
+        let lcm = monkeys
+            .iter()
+            .map(|m| m.test_divisor().to_owned())
+            .fold(ItemWorryLevel::from(1u8), |acc, d| lcm(&acc, &d));
+        Ok(ModuloLcm(lcm))
+    }
+
+    pub fn modulus(&self) -> &ItemWorryLevel {
+        &self.0
+    }
+}
+impl WorryReducer for ModuloLcm {
+    fn reduce(&self, worry: ItemWorryLevel) -> ItemWorryLevel {
+        worry % self.modulus()
+    }
+}
+
+/// Leave worry levels untouched. They will overflow quickly without the `bigint` feature
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct NoReduction;
+impl WorryReducer for NoReduction {
+    fn reduce(&self, worry: ItemWorryLevel) -> ItemWorryLevel {
+        worry
+    }
+}
+
+fn only_adds_and_multiplies(expr: &Expr) -> bool {
+    match expr {
+        Expr::Old | Expr::Const(_) => true,
+        Expr::Binary(lhs, op, rhs) => {
+            matches!(op, BinOp::Add | BinOp::Mul)
+                && only_adds_and_multiplies(lhs)
+                && only_adds_and_multiplies(rhs)
+        }
+    }
+}
+
+fn gcd(a: &ItemWorryLevel, b: &ItemWorryLevel) -> ItemWorryLevel {
+    if b.is_zero() {
+        a.to_owned()
+    } else {
+        gcd(b, &(a % b))
+    }
+}
+
+fn lcm(a: &ItemWorryLevel, b: &ItemWorryLevel) -> ItemWorryLevel {
+    if a.is_zero() || b.is_zero() {
+        return ItemWorryLevel::zero();
+    }
+    a / gcd(a, b) * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_monkeys;
+
+    #[test]
+    fn lcm_of_composite_divisors() {
+        let w = |v: u32| ItemWorryLevel::from(v);
+        assert_eq!(lcm(&w(4), &w(6)), w(12));
+        assert_eq!(lcm(&w(12), &w(18)), w(36));
+        assert_eq!(ModuloLcm(w(12)).reduce(w(30)), w(6));
+    }
+
+    #[test]
+    fn lcm_rejects_division() {
+        let monkeys = parse_monkeys(
+            "\
+Monkey 0:
+  Starting items: 10
+  Operation: new = old * 2
+  Test: divisible by 3
+    If true: throw to monkey 1
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 7
+  Operation: new = (old + 4) / 2
+  Test: divisible by 5
+    If true: throw to monkey 0
+    If false: throw to monkey 0
+",
+        )
+        .unwrap();
+        assert_eq!(
+            ModuloLcm::for_monkeys(&monkeys),
+            Err(ReducerError::UnsupportedOperation {
+                monkey: 1,
+                operation: "(old + 4) / 2".to_string()
+            })
+        );
+        assert!(ModuloLcm::for_monkeys(&monkeys[..1]).is_ok());
+    }
+
+    #[test]
+    fn zero_divisors() {
+        let zero = ItemWorryLevel::zero();
+        assert_eq!(
+            DivideBy::new(ItemWorryLevel::zero()),
+            Err(ReducerError::DivisionByZero)
+        );
+        let monkey = Monkey::new(4, vec![], Expr::Old, zero, 0, 0);
+        assert_eq!(
+            ModuloLcm::for_monkeys(&[monkey]),
+            Err(ReducerError::ZeroTestDivisor(4))
+        );
+    }
+}
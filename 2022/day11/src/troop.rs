@@ -0,0 +1,358 @@
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+use log::debug;
+
+use crate::{
+    monkey::{Item, ItemWorryLevel, Monkey, MonkeyError},
+    reducer::WorryReducer,
+};
+
+/// An item was inspected by `monkey` in `round`, counting from 0
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub struct Visit {
+    pub round: usize,
+    pub monkey: usize,
+}
+
+/// The rounds after which an item repeats its path, with the same worry level
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct ItemCycle {
+    /// Rounds until the item enters the cycle
+    pub start: usize,
+    /// Rounds per cycle
+    pub length: usize,
+    /// Monkeys inspecting the item during one cycle, in order
+    pub monkeys: Vec<usize>,
+}
+
+/// Where an item is at the start of a round
+type ItemState = (usize, ItemWorryLevel);
+
+/// The path of a single item, simulated on its own
+struct ItemRun {
+    /// State at the start of every simulated round
+    states: Vec<ItemState>,
+    /// Monkeys inspecting the item in every simulated round
+    inspections: Vec<Vec<usize>>,
+    /// Round at which the state after the last simulated round was seen before
+    cycle_start: Option<usize>,
+    end: ItemState,
+}
+
+/// All monkeys playing keep away together
+pub struct Troop {
+    monkeys: Vec<Monkey>,
+    reducer: Box<dyn WorryReducer>,
+    round: usize,
+    traces: Option<BTreeMap<usize, Vec<Visit>>>,
+}
+impl Troop {
+    /// Create a troop. Items are numbered across all monkeys, in input order
+    pub fn new(mut monkeys: Vec<Monkey>, reducer: impl WorryReducer + 'static) -> Self {
+        let mut next_id = 0;
+        for m in &mut monkeys {
+            m.number_items(&mut next_id);
+        }
+        Troop {
+            monkeys,
+            reducer: Box::new(reducer),
+            round: 0,
+            traces: None,
+        }
+    }
+
+    /// Record the path of every item from now on, see [`Troop::traces`]
+    pub fn with_tracing(mut self) -> Self {
+        self.traces = Some(BTreeMap::new());
+        self
+    }
+
+    pub fn monkeys(&self) -> &[Monkey] {
+        &self.monkeys
+    }
+
+    /// Number of rounds played so far
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    /// The inspections of every item by item ID, if tracing is enabled
+    pub fn traces(&self) -> Option<&BTreeMap<usize, Vec<Visit>>> {
+        self.traces.as_ref()
+    }
+
+    /// Product of the inspection counts of the two most active monkeys
+    pub fn monkey_business(&self) -> u64 {
+        self.monkeys
+            .iter()
+            .map(|m| m.inspect_count())
+            .sorted()
+            .rev()
+            .take(2)
+            .product()
+    }
+
+    /// Let every monkey inspect and throw the items it holds at the start of its turn
+    pub fn run_round(&mut self) -> Result<(), MonkeyError> {
+        for i in 0..self.monkeys.len() {
+            for _ in 0..self.monkeys[i].items().len() {
+                let (target, item) = match self.monkeys[i].inspect_next(self.reducer.as_ref()) {
+                    Some(thrown) => thrown?,
+                    None => break,
+                };
+                if let Some(traces) = &mut self.traces {
+                    traces.entry(item.id).or_default().push(Visit {
+                        round: self.round,
+                        monkey: i,
+                    });
+                }
+                self.monkeys
+                    .get_mut(target)
+                    .ok_or(MonkeyError::MonkeyNotFound(target))?
+                    .take_item(item);
+            }
+        }
+        self.round += 1;
+        Ok(())
+    }
+
+    pub fn run(&mut self, rounds: usize) -> Result<(), MonkeyError> {
+        for _ in 0..rounds {
+            self.run_round()?;
+        }
+        Ok(())
+    }
+
+    /// Play `rounds` rounds by following every item on its own and skipping ahead once it
+    /// repeats a previous position and worry level.
+    ///
+    /// Items don't influence each other, so inspection counts and item positions end up the same
+    /// as with [`Troop::run`]. The order of the items held by each monkey may differ, and no
+    /// traces are recorded. This only speeds things up if worry levels repeat, such as with
+    /// [`crate::reducer::ModuloLcm`].
+    pub fn run_with_cycle_detection(&mut self, rounds: usize) -> Result<(), MonkeyError> {
+        let mut counts = vec![0; self.monkeys.len()];
+        let mut placed = Vec::new();
+        for (monkey, item) in self.items() {
+            let run = self.follow_item((monkey, item.worry.to_owned()), rounds)?;
+            let (target, worry) = match run.cycle_start {
+                Some(start) => {
+                    let length = run.states.len() - start;
+                    let remaining = rounds - run.states.len();
+                    let (full, rest) = (remaining / length, remaining % length);
+                    debug!(
+                        "Item {} repeats every {} rounds after {} rounds",
+                        item.id, length, start
+                    );
+                    for (round, inspections) in run.inspections.iter().enumerate() {
+                        let repeats = if round < start {
+                            0
+                        } else if round < start + rest {
+                            full + 1
+                        } else {
+                            full
+                        };
+                        for m in inspections {
+                            counts[*m] += repeats as u64;
+                        }
+                    }
+                    run.states[start + rest].to_owned()
+                }
+                None => run.end,
+            };
+            for inspections in &run.inspections {
+                for m in inspections {
+                    counts[*m] += 1;
+                }
+            }
+            placed.push((target, Item { id: item.id, worry }));
+        }
+
+        for m in &mut self.monkeys {
+            m.clear_items();
+        }
+        for (target, item) in placed.into_iter().sorted_by_key(|(_, i)| i.id) {
+            self.monkeys[target].take_item(item);
+        }
+        for (m, count) in self.monkeys.iter_mut().zip(counts) {
+            m.add_inspections(count);
+        }
+        self.round += rounds;
+        Ok(())
+    }
+
+    /// Find the cycle of an item within the next `max_rounds` rounds
+    pub fn item_cycle(
+        &self,
+        item: usize,
+        max_rounds: usize,
+    ) -> Result<Option<ItemCycle>, MonkeyError> {
+        let Some((monkey, item)) = self.items().find(|(_, i)| i.id == item) else {
+            return Ok(None);
+        };
+        let run = self.follow_item((monkey, item.worry.to_owned()), max_rounds)?;
+        Ok(run.cycle_start.map(|start| ItemCycle {
+            start,
+            length: run.states.len() - start,
+            monkeys: run.inspections[start..].concat(),
+        }))
+    }
+
+    /// All items with the monkey holding them
+    fn items(&self) -> impl Iterator<Item = (usize, &Item)> {
+        self.monkeys
+            .iter()
+            .enumerate()
+            .flat_map(|(i, m)| m.items().iter().map(move |item| (i, item)))
+    }
+
+    /// Simulate a single item for up to `max_rounds` rounds, stopping early once it repeats
+    fn follow_item(&self, start: ItemState, max_rounds: usize) -> Result<ItemRun, MonkeyError> {
+        let mut seen: HashMap<ItemState, usize> = HashMap::new();
+        let mut run = ItemRun {
+            states: Vec::new(),
+            inspections: Vec::new(),
+            cycle_start: None,
+            end: start,
+        };
+        while run.states.len() < max_rounds {
+            if let Some(round) = seen.get(&run.end) {
+                run.cycle_start = Some(*round);
+                break;
+            }
+            seen.insert(run.end.to_owned(), run.states.len());
+            let (next, inspections) = self.item_round(&run.end)?;
+            run.states.push(std::mem::replace(&mut run.end, next));
+            run.inspections.push(inspections);
+        }
+        Ok(run)
+    }
+
+    /// Follow an item through a single round. Throws to a monkey that comes later in the round
+    /// are inspected right away, others wait for the next round
+    fn item_round(&self, state: &ItemState) -> Result<(ItemState, Vec<usize>), MonkeyError> {
+        let (mut monkey, mut worry) = state.to_owned();
+        let mut inspections = Vec::new();
+        loop {
+            let (new_worry, target) =
+                self.monkeys[monkey].process(&worry, self.reducer.as_ref())?;
+            inspections.push(monkey);
+            if target >= self.monkeys.len() {
+                return Err(MonkeyError::MonkeyNotFound(target));
+            }
+            if target <= monkey {
+                return Ok(((target, new_worry), inspections));
+            }
+            (monkey, worry) = (target, new_worry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::parse_monkeys,
+        reducer::{DivideBy, ModuloLcm},
+    };
+
+    const EXAMPLE: &str = "\
+Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+    fn troop(reducer: impl WorryReducer + 'static) -> Troop {
+        Troop::new(parse_monkeys(EXAMPLE).unwrap(), reducer)
+    }
+
+    #[test]
+    fn example_reducers() {
+        let mut relief = troop(DivideBy::new(ItemWorryLevel::from(3u8)).unwrap());
+        relief.run(20).unwrap();
+        assert_eq!(relief.monkey_business(), 10605);
+
+        let monkeys = parse_monkeys(EXAMPLE).unwrap();
+        let mut panic = Troop::new(monkeys.clone(), ModuloLcm::for_monkeys(&monkeys).unwrap());
+        panic.run(10000).unwrap();
+        assert_eq!(panic.monkey_business(), 2713310158);
+    }
+
+    #[test]
+    fn cycle_detection_matches_simulation() {
+        let monkeys = parse_monkeys(EXAMPLE).unwrap();
+        let lcm = ModuloLcm::for_monkeys(&monkeys).unwrap();
+        let mut simulated = Troop::new(monkeys.clone(), lcm.clone());
+        let mut jumped = Troop::new(monkeys, lcm);
+        simulated.run(10000).unwrap();
+        jumped.run_with_cycle_detection(10000).unwrap();
+        assert_eq!(jumped.round(), 10000);
+        assert_eq!(jumped.monkey_business(), 2713310158);
+        for (s, j) in simulated.monkeys().iter().zip(jumped.monkeys()) {
+            assert_eq!(s.inspect_count(), j.inspect_count());
+            let mut items = s.items().iter().cloned().collect_vec();
+            items.sort_by_key(|i| i.id);
+            assert_eq!(items, j.items().iter().cloned().collect_vec());
+        }
+    }
+
+    #[test]
+    fn item_traces() {
+        let mut troop = troop(DivideBy::new(ItemWorryLevel::from(3u8)).unwrap()).with_tracing();
+        troop.run(1).unwrap();
+        // Item 0 starts at monkey 0 with worry 79, goes to monkey 3 with 500, then to monkey 1
+        let visits = &troop.traces().unwrap()[&0];
+        assert_eq!(
+            visits,
+            &[
+                Visit {
+                    round: 0,
+                    monkey: 0
+                },
+                Visit {
+                    round: 0,
+                    monkey: 3
+                }
+            ]
+        );
+        assert!(troop.monkeys()[1].items().contains(&Item {
+            id: 0,
+            worry: ItemWorryLevel::from(167u8)
+        }));
+    }
+
+    #[test]
+    fn item_cycles() {
+        let monkeys = parse_monkeys(EXAMPLE).unwrap();
+        let troop = Troop::new(monkeys.clone(), ModuloLcm::for_monkeys(&monkeys).unwrap());
+        let cycle = troop.item_cycle(0, 100_000).unwrap().unwrap();
+        assert!(cycle.length > 0);
+        assert!(cycle.monkeys.len() >= cycle.length);
+        assert_eq!(troop.item_cycle(100, 10).unwrap(), None);
+    }
+}
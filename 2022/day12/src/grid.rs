@@ -1,4 +1,30 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Error)]
+pub enum GridError {
+    #[error("({x},{y}) is outside of the grid")]
+    OutOfBounds { x: usize, y: usize },
+    #[error("The heightmap is empty")]
+    Empty,
+    #[error("Line {line} has {len} cells, expected {expected}")]
+    RaggedLine {
+        line: usize,
+        len: usize,
+        expected: usize,
+    },
+    #[error("Line {line}, column {column}: '{found}' is not a valid elevation")]
+    InvalidChar {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    #[error("Expected exactly one start position 'S', found {0}")]
+    StartCount(usize),
+    #[error("Expected exactly one end position 'E', found {0}")]
+    EndCount(usize),
+}
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Grid {
@@ -9,9 +35,12 @@ pub struct Grid {
     end: Option<(usize, usize)>,
 }
 impl Grid {
+    /// Create a grid with all cells at elevation 0
     pub fn new(width: usize, height: usize) -> Self {
         Grid {
-            cells: vec![],
+            cells: (0..height)
+                .flat_map(|y| (0..width).map(move |x| Cell { x, y, elevation: 0 }))
+                .collect(),
             width,
             height,
             start: None,
@@ -19,18 +48,11 @@ impl Grid {
         }
     }
 
-    pub fn insert_cell(&mut self, x: usize, y: usize, elevation: u8) -> &Cell {
-        let index = y * self.width + x;
-        let newcell = Cell { x, y, elevation };
-        match self.cells.get(index) {
-            Some(_) => {
-                let _ = std::mem::replace(&mut &self.cells[index], &newcell);
-            }
-            None => {
-                self.cells.insert(index, newcell);
-            }
-        }
-        self.cell_at(x, y).unwrap()
+    /// Set the elevation of the cell at `x`,`y`, replacing the previous one
+    pub fn insert_cell(&mut self, x: usize, y: usize, elevation: u8) -> Result<&Cell, GridError> {
+        let index = self.index_at(x, y).ok_or(GridError::OutOfBounds { x, y })?;
+        self.cells[index] = Cell { x, y, elevation };
+        Ok(&self.cells[index])
     }
     pub fn start(&self) -> Option<&Cell> {
         self.start.and_then(|(x, y)| self.cell_at(x, y))
@@ -46,15 +68,44 @@ impl Grid {
     }
 
     pub fn cell_at(&self, x: usize, y: usize) -> Option<&Cell> {
-        self.cells.get(y * self.width + x)
+        self.cells.get(self.index_at(x, y)?)
+    }
+    fn index_at(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then_some(y * self.width + x)
     }
-    pub fn cell_by_id(&self, id: u32) -> Option<&Cell> {
-        self.cells.get(id as usize)
+    pub fn cell_by_index(&self, index: usize) -> Option<&Cell> {
+        self.cells.get(index)
+    }
+    /// Position of the cell in row-major order, usable with [`Grid::cell_by_index`].
+    /// `None` if the cell is outside of the grid
+    pub fn index_of(&self, cell: &Cell) -> Option<usize> {
+        self.index_at(cell.x, cell.y)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
     }
 
     pub fn cells(&self) -> impl Iterator<Item = &Cell> {
         self.cells.iter()
     }
+    /// The up to four cells directly next to `cell`
+    pub fn neighbors<'a>(&'a self, cell: &'a Cell) -> impl Iterator<Item = &'a Cell> {
+        [
+            self.cell_top(cell),
+            self.cell_bot(cell),
+            self.cell_left(cell),
+            self.cell_right(cell),
+        ]
+        .into_iter()
+        .flatten()
+    }
     pub fn cell_top(&self, cell: &Cell) -> Option<&Cell> {
         if cell.y == 0 {
             return None;
@@ -74,9 +125,62 @@ impl Grid {
         self.cell_at(cell.x + 1, cell.y)
     }
 }
+impl FromStr for Grid {
+    type Err = GridError;
+
+    /// Parse a heightmap of `a-z`, with exactly one start `S` at `a` and one end `E` at `z`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().collect();
+        let width = lines.first().map_or(0, |l| l.len());
+        if width == 0 {
+            return Err(GridError::Empty);
+        }
+        let mut grid = Grid::new(width, lines.len());
+        let (mut starts, mut ends) = (0, 0);
+        for (y, l) in lines.iter().enumerate() {
+            if l.len() != width {
+                return Err(GridError::RaggedLine {
+                    line: y + 1,
+                    len: l.len(),
+                    expected: width,
+                });
+            }
+            for (x, c) in l.chars().enumerate() {
+                let elevation = match c {
+                    'S' => {
+                        starts += 1;
+                        grid.set_start(x, y);
+                        b'a'
+                    }
+                    'E' => {
+                        ends += 1;
+                        grid.set_end(x, y);
+                        b'z'
+                    }
+                    c if c.is_ascii_lowercase() => c as u8,
+                    _ => {
+                        return Err(GridError::InvalidChar {
+                            line: y + 1,
+                            column: x + 1,
+                            found: c,
+                        })
+                    }
+                };
+                grid.insert_cell(x, y, elevation)?;
+            }
+        }
+        if starts != 1 {
+            return Err(GridError::StartCount(starts));
+        }
+        if ends != 1 {
+            return Err(GridError::EndCount(ends));
+        }
+        Ok(grid)
+    }
+}
 impl Display for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for l in self.cells.chunks_exact(self.width) {
+        for l in self.cells.chunks_exact(self.width.max(1)) {
             writeln!(
                 f,
                 "|{}|",
@@ -97,8 +201,9 @@ pub struct Cell {
     pub elevation: u8,
 }
 impl Cell {
+    /// Cost of stepping onto `other`, or `None` if it is not a neighbour or more than one higher
     pub fn cost_to(&self, other: &Cell) -> Option<u8> {
-        if other.elevation > self.elevation + 1 || !self.is_neighbor(other) {
+        if other.elevation.saturating_sub(self.elevation) > 1 || !self.is_neighbor(other) {
             return None;
         }
         Some(1)
@@ -116,3 +221,74 @@ impl Display for Cell {
         write!(f, "({},{}):{}", self.x, self.y, self.elevation)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_out_of_order() {
+        let mut grid = Grid::new(3, 2);
+        grid.insert_cell(2, 1, b'c').unwrap();
+        grid.insert_cell(0, 0, b'a').unwrap();
+        grid.insert_cell(2, 1, b'z').unwrap();
+        assert_eq!(grid.cell_at(2, 1).unwrap().elevation, b'z');
+        assert_eq!(grid.cell_at(0, 0).unwrap().elevation, b'a');
+        assert_eq!(grid.cell_count(), 6);
+        assert_eq!(
+            grid.insert_cell(3, 0, b'a'),
+            Err(GridError::OutOfBounds { x: 3, y: 0 })
+        );
+    }
+
+    #[test]
+    fn climbing_from_the_highest_elevation() {
+        let top = Cell {
+            x: 0,
+            y: 0,
+            elevation: u8::MAX,
+        };
+        let next = Cell { x: 1, ..top };
+        assert_eq!(top.cost_to(&next), Some(1));
+        assert_eq!(
+            Cell {
+                elevation: 0,
+                ..next
+            }
+            .cost_to(&top),
+            None
+        );
+    }
+
+    #[test]
+    fn neighbours_stay_in_bounds() {
+        let grid: Grid = "Sab\ncdE".parse().unwrap();
+        let corner = *grid.cell_at(2, 0).unwrap();
+        assert_eq!(grid.cell_right(&corner), None);
+        assert_eq!(grid.cell_bot(grid.cell_at(0, 1).unwrap()), None);
+        assert_eq!(grid.neighbors(&corner).count(), 2);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "abc\nSE".parse::<Grid>(),
+            Err(GridError::RaggedLine {
+                line: 2,
+                len: 2,
+                expected: 3
+            })
+        );
+        assert_eq!("aSb\nEcS".parse::<Grid>(), Err(GridError::StartCount(2)));
+        assert_eq!("aSb\nacd".parse::<Grid>(), Err(GridError::EndCount(0)));
+        assert!(matches!(
+            "aS1\nEcd".parse::<Grid>(),
+            Err(GridError::InvalidChar {
+                line: 1,
+                column: 3,
+                found: '1'
+            })
+        ));
+        assert_eq!("".parse::<Grid>(), Err(GridError::Empty));
+    }
+}
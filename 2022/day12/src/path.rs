@@ -0,0 +1,234 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use thiserror::Error;
+
+use crate::grid::{Cell, Grid};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Error)]
+pub enum PathError {
+    #[error("Cell {0} is outside of the grid")]
+    OutsideGrid(Cell),
+    #[error("Step from {from} to {to} doesn't go to a neighbouring cell")]
+    NotAdjacent { from: Cell, to: Cell },
+}
+
+/// A walk across the grid, from the first to the last cell
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct Path<'a>(pub Vec<&'a Cell>);
+impl<'a> Path<'a> {
+    /// Number of steps taken
+    pub fn cost(&self) -> usize {
+        self.0.len().saturating_sub(1)
+    }
+
+    /// Draw the path onto the map, with arrows pointing to the next step and `E` at the end.
+    /// Cells not on the path are drawn as `.`
+    pub fn render(&self, grid: &Grid) -> Result<String, PathError> {
+        let mut map = vec![vec!['.'; grid.width()]; grid.height()];
+        if let Some(c) = self.0.iter().find(|c| grid.cell_at(c.x, c.y).is_none()) {
+            return Err(PathError::OutsideGrid(**c));
+        }
+        for step in self.0.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            map[from.y][from.x] = match (
+                to.x as isize - from.x as isize,
+                to.y as isize - from.y as isize,
+            ) {
+                (1, 0) => '>',
+                (-1, 0) => '<',
+                (0, 1) => 'v',
+                (0, -1) => '^',
+                _ => {
+                    return Err(PathError::NotAdjacent {
+                        from: *from,
+                        to: *to,
+                    })
+                }
+            };
+        }
+        if let Some(last) = self.0.last() {
+            map[last.y][last.x] = 'E';
+        }
+        Ok(map
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect())
+    }
+}
+impl<'a> Display for Path<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, c) in self.0.iter().enumerate() {
+            if i + 1 == self.0.len() {
+                write!(f, "{}", c)?
+            } else {
+                write!(f, "{} -> ", c)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shortest distances from every cell to a single target, see [`distances_to`]
+#[derive(Clone, Debug)]
+pub struct DistanceMap<'a> {
+    grid: &'a Grid,
+    distances: Vec<Option<usize>>,
+    /// The next cell on a shortest path towards the target
+    next: Vec<Option<usize>>,
+}
+impl<'a> DistanceMap<'a> {
+    /// Steps needed to reach the target from `cell`, or `None` if it can't be reached
+    pub fn distance(&self, cell: &Cell) -> Option<usize> {
+        self.distances
+            .get(self.grid.index_of(cell)?)
+            .copied()
+            .flatten()
+    }
+
+    /// A shortest path from `cell` to the target
+    pub fn path_from(&self, cell: &Cell) -> Option<Path<'a>> {
+        let mut index = self.grid.index_of(cell)?;
+        self.distances.get(index).copied().flatten()?;
+        let mut path = vec![self.grid.cell_by_index(index)?];
+        while let Some(next) = self.next[index] {
+            index = next;
+            path.push(self.grid.cell_by_index(index)?);
+        }
+        Some(Path(path))
+    }
+
+    /// The cell closest to the target out of those matching `filter`, with its distance
+    pub fn closest(&self, filter: impl Fn(&Cell) -> bool) -> Option<(&'a Cell, usize)> {
+        self.grid
+            .cells()
+            .filter(|c| filter(c))
+            .filter_map(|c| self.distance(c).map(|d| (c, d)))
+            .min_by_key(|(_, d)| *d)
+    }
+}
+
+/// Breadth-first search backwards from `target`, following [`Cell::cost_to`] edges in reverse.
+///
+/// All steps are treated as equally long, so the result is the shortest distance from every cell
+/// to the target at once.
+pub fn distances_to<'a>(grid: &'a Grid, target: &Cell) -> DistanceMap<'a> {
+    let mut distances = vec![None; grid.cell_count()];
+    let mut next = vec![None; grid.cell_count()];
+    let mut queue = VecDeque::new();
+
+    if let Some(start) = grid.index_of(target) {
+        distances[start] = Some(0);
+        queue.push_back(start);
+    }
+    while let Some(index) = queue.pop_front() {
+        let cell = grid.cell_by_index(index).unwrap();
+        let distance = distances[index].unwrap();
+        for from in grid.neighbors(cell) {
+            // Neighbours always come from the grid
+            let from_index = grid.index_of(from).unwrap();
+            if distances[from_index].is_none() && from.cost_to(cell).is_some() {
+                distances[from_index] = Some(distance + 1);
+                next[from_index] = Some(index);
+                queue.push_back(from_index);
+            }
+        }
+    }
+    DistanceMap {
+        grid,
+        distances,
+        next,
+    }
+}
+
+/// Shortest path from `from` to `to`, using a forward breadth-first search
+pub fn shortest_path<'a>(grid: &'a Grid, from: &Cell, to: &Cell) -> Option<Path<'a>> {
+    let mut previous: Vec<Option<usize>> = vec![None; grid.cell_count()];
+    let mut visited = vec![false; grid.cell_count()];
+    let mut queue = VecDeque::new();
+
+    let (start, goal) = (grid.index_of(from)?, grid.index_of(to)?);
+    visited[start] = true;
+    queue.push_back(start);
+    while let Some(index) = queue.pop_front() {
+        if index == goal {
+            let mut path = vec![grid.cell_by_index(index)?];
+            let mut current = index;
+            while let Some(p) = previous[current] {
+                path.push(grid.cell_by_index(p)?);
+                current = p;
+            }
+            path.reverse();
+            return Some(Path(path));
+        }
+        let cell = grid.cell_by_index(index)?;
+        for neighbor in grid.neighbors(cell) {
+            let n = grid.index_of(neighbor).unwrap();
+            if !visited[n] && cell.cost_to(neighbor).is_some() {
+                visited[n] = true;
+                previous[n] = Some(index);
+                queue.push_back(n);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Sabqponm\nabcryxxl\naccszExk\nacctuvwj\nabdefghi";
+
+    fn example() -> Grid {
+        EXAMPLE.parse().unwrap()
+    }
+
+    #[test]
+    fn example_paths() {
+        let grid = example();
+        let (start, end) = (grid.start().unwrap(), grid.end().unwrap());
+        let distances = distances_to(&grid, end);
+        assert_eq!(distances.distance(start), Some(31));
+        assert_eq!(distances.closest(|c| c.elevation == b'a').unwrap().1, 29);
+
+        let forward = shortest_path(&grid, start, end).unwrap();
+        let backward = distances.path_from(start).unwrap();
+        assert_eq!(forward.cost(), 31);
+        assert_eq!(backward.cost(), 31);
+        assert_eq!(backward.0.first(), Some(&start));
+        assert_eq!(backward.0.last(), Some(&end));
+        assert!(backward.0.windows(2).all(|s| s[0].cost_to(s[1]).is_some()));
+        let map = backward.render(&grid).unwrap();
+        assert_eq!(map.lines().count(), 5);
+        assert_eq!(map.matches('E').count(), 1);
+        assert_eq!(map.chars().filter(|c| "<>^v".contains(*c)).count(), 31);
+
+        let (a, b) = (grid.cell_at(0, 0).unwrap(), grid.cell_at(1, 1).unwrap());
+        assert_eq!(
+            Path(vec![a, b]).render(&grid),
+            Err(PathError::NotAdjacent { from: *a, to: *b })
+        );
+    }
+
+    #[test]
+    fn cells_outside_of_the_grid() {
+        let grid = example();
+        let start = grid.start().unwrap();
+        // Would alias (0,1) if only the index was checked
+        let outside = Cell {
+            x: grid.width(),
+            y: 0,
+            elevation: b'a',
+        };
+        assert_eq!(grid.index_of(&outside), None);
+
+        let distances = distances_to(&grid, grid.end().unwrap());
+        assert_eq!(distances.distance(&outside), None);
+        assert!(distances.path_from(&outside).is_none());
+        assert!(shortest_path(&grid, start, &outside).is_none());
+        assert!(shortest_path(&grid, &outside, start).is_none());
+
+        let unreachable = distances_to(&grid, &outside);
+        assert!(grid.cells().all(|c| unreachable.distance(c).is_none()));
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    cmp::Ordering::{self, Equal, Greater, Less},
+    fmt::Display,
+};
+
+use crate::packet::Element;
+
+/// One step of a comparison, nested `depth` levels into the packets
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TraceStep {
+    pub depth: usize,
+    pub message: String,
+}
+
+/// Explanation of a comparison, in the format used by the puzzle description
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Trace(pub Vec<TraceStep>);
+impl Trace {
+    fn push(&mut self, depth: usize, message: String) {
+        self.0.push(TraceStep { depth, message })
+    }
+}
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.0 {
+            writeln!(f, "{}- {}", "  ".repeat(step.depth), step.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two packet elements by the distress signal rules
+pub fn compare(left: &Element, right: &Element) -> Ordering {
+    compare_elements(left, right, 0, &mut None)
+}
+
+/// Like [`compare`], but also explain every step that led to the result
+pub fn compare_traced(left: &Element, right: &Element) -> (Ordering, Trace) {
+    let mut trace = Some(Trace::default());
+    let ordering = compare_elements(left, right, 0, &mut trace);
+    (ordering, trace.unwrap_or_default())
+}
+
+fn compare_elements(
+    left: &Element,
+    right: &Element,
+    depth: usize,
+    trace: &mut Option<Trace>,
+) -> Ordering {
+    if let Some(t) = trace {
+        t.push(depth, format!("Compare {} vs {}", left, right));
+    }
+    match (left, right) {
+        (Element::List(l), Element::List(r)) => compare_lists(l, r, depth + 1, trace),
+        (Element::List(_), Element::Int(r)) => {
+            if let Some(t) = trace {
+                t.push(
+                    depth + 1,
+                    format!("Mixed types; convert right to [{}] and retry comparison", r),
+                );
+            }
+            let right = Element::List(vec![Element::Int(*r)]);
+            compare_elements(left, &right, depth + 1, trace)
+        }
+        (Element::Int(l), Element::List(_)) => {
+            if let Some(t) = trace {
+                t.push(
+                    depth + 1,
+                    format!("Mixed types; convert left to [{}] and retry comparison", l),
+                );
+            }
+            let left = Element::List(vec![Element::Int(*l)]);
+            compare_elements(&left, right, depth + 1, trace)
+        }
+        (Element::Int(l), Element::Int(r)) => {
+            let ordering = l.cmp(r);
+            if let Some(t) = trace {
+                match ordering {
+                    Less => t.push(
+                        depth + 1,
+                        "Left side is smaller, so inputs are in the right order".to_string(),
+                    ),
+                    Greater => t.push(
+                        depth + 1,
+                        "Right side is smaller, so inputs are not in the right order".to_string(),
+                    ),
+                    Equal => {}
+                }
+            }
+            ordering
+        }
+    }
+}
+
+fn compare_lists(
+    l: &[Element],
+    r: &[Element],
+    depth: usize,
+    trace: &mut Option<Trace>,
+) -> Ordering {
+    for (e_left, e_right) in l.iter().zip(r) {
+        match compare_elements(e_left, e_right, depth, trace) {
+            Equal => continue,
+            ordering => return ordering,
+        }
+    }
+    let ordering = l.len().cmp(&r.len());
+    if let Some(t) = trace {
+        match ordering {
+            Less => t.push(
+                depth,
+                "Left side ran out of items, so inputs are in the right order".to_string(),
+            ),
+            Greater => t.push(
+                depth,
+                "Right side ran out of items, so inputs are not in the right order".to_string(),
+            ),
+            Equal => {}
+        }
+    }
+    ordering
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_pairs, Backend};
+
+    #[test]
+    fn trace() {
+        let pairs = parse_pairs("[[1],[2,3,4]]\n[[1],4]\n", Backend::Nom).unwrap();
+        let (ordering, trace) = compare_traced(&pairs[0].left.0, &pairs[0].right.0);
+        assert_eq!(ordering, Less);
+        assert_eq!(
+            trace.to_string(),
+            "\
+- Compare [[1],[2,3,4]] vs [[1],4]
+  - Compare [1] vs [1]
+    - Compare 1 vs 1
+  - Compare [2,3,4] vs 4
+    - Mixed types; convert right to [4] and retry comparison
+    - Compare [2,3,4] vs [4]
+      - Compare 2 vs 4
+        - Left side is smaller, so inputs are in the right order
+"
+        );
+        assert_eq!(compare(&pairs[0].left.0, &pairs[0].right.0), ordering);
+
+        let pairs = parse_pairs("[9]\n[[8,7,6]]\n", Backend::Nom).unwrap();
+        let (ordering, trace) = compare_traced(&pairs[0].left.0, &pairs[0].right.0);
+        assert_eq!(ordering, Greater);
+        assert_eq!(
+            trace.to_string(),
+            "\
+- Compare [9] vs [[8,7,6]]
+  - Compare 9 vs [8,7,6]
+    - Mixed types; convert left to [9] and retry comparison
+    - Compare [9] vs [8,7,6]
+      - Compare 9 vs 8
+        - Right side is smaller, so inputs are not in the right order
+"
+        );
+    }
+}
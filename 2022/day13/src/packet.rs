@@ -0,0 +1,73 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use crate::compare::compare;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Pair {
+    pub left: Packet,
+    pub right: Packet,
+}
+impl Pair {
+    /// Whether the pair is in the right order, i.e. left sorts before right
+    pub fn is_ordered(&self) -> bool {
+        self.left.compare(&self.right) == Ordering::Less
+    }
+}
+
+/// A packet. Equality is structural, so `[2]` and `[[2]]` differ even though they compare as
+/// [`Ordering::Equal`] by the distress signal rules, see [`Packet::compare`]
+#[derive(Clone, PartialEq, Hash, Debug, Eq)]
+pub struct Packet(pub Element);
+impl Packet {
+    /// Order two packets by the distress signal rules, see [`compare`]
+    pub fn compare(&self, other: &Packet) -> Ordering {
+        compare(&self.0, &other.0)
+    }
+}
+impl Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// An integer or a list inside a packet. Order elements with [`compare`]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Element {
+    List(Vec<Element>),
+    Int(i32),
+}
+impl Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Element::List(l) => {
+                write!(f, "[")?;
+                for i in 0..l.len() {
+                    if i + 1 == l.len() {
+                        write!(f, "{}", l[i])?;
+                    } else {
+                        write!(f, "{},", l[i])?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Element::Int(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+/// The two extra packets added when decoding the distress signal, `[[2]]` and `[[6]]`
+pub fn divider_packets() -> [Packet; 2] {
+    [2, 6].map(|i| Packet(Element::List(vec![Element::List(vec![Element::Int(i)])])))
+}
+
+/// Sort `packets` together with the [`divider_packets`] and multiply the 1-based positions of
+/// the dividers
+pub fn decoder_key(packets: impl IntoIterator<Item = Packet>) -> usize {
+    let dividers = divider_packets();
+    let mut sorted: Vec<Packet> = packets.into_iter().chain(dividers.clone()).collect();
+    sorted.sort_by(Packet::compare);
+    dividers
+        .iter()
+        .map(|d| sorted.iter().position(|p| p == d).unwrap() + 1)
+        .product()
+}
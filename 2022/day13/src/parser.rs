@@ -0,0 +1,282 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::newline,
+    combinator::{all_consuming, map, opt},
+    multi::separated_list0,
+    sequence::{delimited, separated_pair, terminated},
+    Finish, IResult,
+};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::packet::{Element, Packet, Pair};
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("Parsing Error: {0}")]
+    Nom(String),
+    #[error("Line {line}: invalid JSON")]
+    Json {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Line {line}: {value} is not a valid packet element")]
+    InvalidElement { line: usize, value: Value },
+    #[error("Line {line}: packet has no partner")]
+    Unpaired { line: usize },
+    #[error("Line {line}: a pair has only two packets")]
+    ExtraPacket { line: usize },
+    #[error("Line {line}: unexpected blank line")]
+    BlankLine { line: usize },
+    #[error("Line {line}: packets may not contain whitespace")]
+    Whitespace { line: usize },
+}
+
+/// How to parse packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Backend {
+    /// The hand-written nom parser
+    #[default]
+    Nom,
+    /// Read each packet as JSON with serde_json
+    Json,
+}
+
+/// Parse pairs of packets, separated by blank lines.
+///
+/// Both backends expect the same layout: two packets per pair, a single blank line between pairs
+/// and at most one newline at the end
+pub fn parse_pairs(input: &str, backend: Backend) -> Result<Vec<Pair>, ParseError> {
+    match backend {
+        Backend::Nom => match all_consuming(packet_pairs)(input).finish() {
+            Ok((_, pairs)) => Ok(pairs),
+            Err(e) => Err(ParseError::Nom(e.to_string())),
+        },
+        Backend::Json => json_pairs(input),
+    }
+}
+
+fn packet_pairs(input: &str) -> IResult<&str, Vec<Pair>> {
+    let p = terminated(
+        separated_list0(tag("\n\n"), separated_pair(packet, tag("\n"), packet)),
+        opt(newline),
+    );
+    map(p, |r| {
+        r.into_iter()
+            .map(|(l, r)| Pair { left: l, right: r })
+            .collect()
+    })(input)
+}
+
+fn packet(input: &str) -> IResult<&str, Packet> {
+    map(list, Packet)(input)
+}
+
+fn list(input: &str) -> IResult<&str, Element> {
+    let p = delimited(tag("["), elements, tag("]"));
+    map(p, Element::List)(input)
+}
+
+fn elements(input: &str) -> IResult<&str, Vec<Element>> {
+    separated_list0(tag(","), alt((list, integer)))(input)
+}
+
+fn integer(input: &str) -> IResult<&str, Element> {
+    map(nom::character::complete::i32, Element::Int)(input)
+}
+
+fn json_pairs(input: &str) -> Result<Vec<Pair>, ParseError> {
+    let body = input.strip_suffix('\n').unwrap_or(input);
+    if body.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut pairs = Vec::new();
+    // Packets of the pair being read, with the line of the first one
+    let mut group: Vec<Packet> = Vec::with_capacity(2);
+    let mut group_line = 0;
+    let mut last_line = 0;
+    for (i, line) in body.split('\n').enumerate() {
+        let line_no = i + 1;
+        last_line = line_no;
+        if line.is_empty() {
+            match group.len() {
+                0 => return Err(ParseError::BlankLine { line: line_no }),
+                1 => return Err(ParseError::Unpaired { line: group_line }),
+                _ => pairs.push(json_pair(&mut group)),
+            }
+            continue;
+        }
+        if group.len() == 2 {
+            return Err(ParseError::ExtraPacket { line: line_no });
+        }
+        if group.is_empty() {
+            group_line = line_no;
+        }
+        group.push(json_packet(line, line_no)?);
+    }
+    match group.len() {
+        // The input ended with a blank line
+        0 => Err(ParseError::BlankLine { line: last_line }),
+        1 => Err(ParseError::Unpaired { line: group_line }),
+        _ => {
+            pairs.push(json_pair(&mut group));
+            Ok(pairs)
+        }
+    }
+}
+
+fn json_pair(group: &mut Vec<Packet>) -> Pair {
+    let right = group.pop().unwrap();
+    let left = group.pop().unwrap();
+    Pair { left, right }
+}
+
+fn json_packet(line: &str, line_no: usize) -> Result<Packet, ParseError> {
+    // serde_json skips whitespace between tokens, the puzzle format has none
+    if line.contains(char::is_whitespace) {
+        return Err(ParseError::Whitespace { line: line_no });
+    }
+    let value = serde_json::from_str(line).map_err(|source| ParseError::Json {
+        line: line_no,
+        source,
+    })?;
+    // Packets are always lists, only their contents may be integers
+    match value {
+        Value::Array(_) => json_element(&value, line_no).map(Packet),
+        _ => Err(ParseError::InvalidElement {
+            line: line_no,
+            value,
+        }),
+    }
+}
+
+fn json_element(value: &Value, line: usize) -> Result<Element, ParseError> {
+    let invalid = || ParseError::InvalidElement {
+        line,
+        value: value.clone(),
+    };
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|v| json_element(v, line))
+            .collect::<Result<_, _>>()
+            .map(Element::List),
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .map(Element::Int)
+            .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+[1,1,3,1,1]
+[1,1,5,1,1]
+
+[[1],[2,3,4]]
+[[1],4]
+
+[9]
+[[8,7,6]]
+
+[[4,4],4,4]
+[[4,4],4,4,4]
+
+[7,7,7,7]
+[7,7,7]
+
+[]
+[3]
+
+[[[]]]
+[[]]
+
+[1,[2,[3,[4,[5,6,7]]]],8,9]
+[1,[2,[3,[4,[5,6,0]]]],8,9]
+";
+
+    #[test]
+    fn backends_agree() {
+        let nom = parse_pairs(EXAMPLE, Backend::Nom).unwrap();
+        let json = parse_pairs(EXAMPLE, Backend::Json).unwrap();
+        assert_eq!(nom, json);
+        let ordered: usize = nom
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_ordered())
+            .map(|(i, _)| i + 1)
+            .sum();
+        assert_eq!(ordered, 13);
+        let packets = nom.into_iter().flat_map(|p| [p.left, p.right]);
+        assert_eq!(crate::packet::decoder_key(packets), 140);
+    }
+
+    #[test]
+    fn json_errors() {
+        assert!(matches!(
+            parse_pairs("[1]\n[1,\"a\"]\n", Backend::Json),
+            Err(ParseError::InvalidElement { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_pairs("[1]\n[1\n", Backend::Json),
+            Err(ParseError::Json { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_pairs("[1]\n[2]\n\n[3]\n", Backend::Json),
+            Err(ParseError::Unpaired { line: 4 })
+        ));
+        assert!(matches!(
+            parse_pairs("[1]\n[2]\n[3]\n[4]\n", Backend::Json),
+            Err(ParseError::ExtraPacket { line: 3 })
+        ));
+        assert!(matches!(
+            parse_pairs("[1]\n[2]\n\n\n[3]\n[4]\n", Backend::Json),
+            Err(ParseError::BlankLine { line: 4 })
+        ));
+        assert!(matches!(
+            parse_pairs("[1]\n[1, 2]\n", Backend::Json),
+            Err(ParseError::Whitespace { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn backends_agree_on_pair_structure() {
+        let well_formed = ["", "[1]\n[2]", "[1]\n[2]\n", "[1]\n[2]\n\n[3]\n[4]\n"];
+        for input in well_formed {
+            let nom = parse_pairs(input, Backend::Nom).unwrap();
+            assert_eq!(
+                nom,
+                parse_pairs(input, Backend::Json).unwrap(),
+                "{:?}",
+                input
+            );
+        }
+        let malformed = [
+            "[1]\n",
+            "[1]\n[2]\n[3]\n",
+            "[1]\n[2]\n[3]\n[4]\n",
+            "[1]\n\n[2]\n",
+            "\n[1]\n[2]\n",
+            "[1]\n[2]\n\n\n[3]\n[4]\n",
+            "[1]\n[2]\n\n",
+            "[1]\n[2]\n\n[3]\n",
+            "[1, 2]\n[3]\n",
+            " [1]\n[2]\n",
+            "[1]\n[2] \n",
+            "[1]\r\n[2]\r\n",
+            "[1]\n[2]\n\r\n[3]\n[4]\n",
+        ];
+        for input in malformed {
+            assert!(parse_pairs(input, Backend::Nom).is_err(), "{:?}", input);
+            assert!(parse_pairs(input, Backend::Json).is_err(), "{:?}", input);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt::{Debug, Display};
 
+use util::taxicab::{ManhattanGeometry, Point};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Grid<T> {
     origin: GridCoord,
@@ -78,3 +80,20 @@ impl Display for GridCoord {
         write!(f, "({},{})", self.x, self.y)
     }
 }
+impl ManhattanGeometry for GridCoord {
+    type Distance = usize;
+
+    fn taxicab_distance(&self, other: &Self) -> Self::Distance {
+        Point::from(*self).taxicab_distance(&Point::from(*other))
+    }
+}
+impl From<GridCoord> for Point {
+    fn from(c: GridCoord) -> Self {
+        Point { x: c.x, y: c.y }
+    }
+}
+impl From<Point> for GridCoord {
+    fn from(p: Point) -> Self {
+        GridCoord { x: p.x, y: p.y }
+    }
+}
@@ -1,29 +1,22 @@
-use std::{collections::HashSet, fmt::Display};
+use std::fmt::Display;
 
 use color_eyre::{eyre::eyre, Result};
 use day15::{
-    grid::{self, Grid, GridCoord, SparseDefaultGrid},
-    sensors::Sensor,
+    grid::SparseDefaultGrid,
+    sensors::{beacon_free_cells_in_row, find_distress_beacon, parse_sensors},
 };
-use itertools::Itertools;
 use log::debug;
-use rayon::prelude::*;
 
 const ROW_TO_CHECK: isize = 2000000;
-const SEARCH_AREA_MIN: isize = 0;
-const SEARCH_AREA_MAX: isize = 4000000;
+const TUNING_MULTIPLIER: isize = 4000000;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default)]
 enum GridElement {
     Sensor,
     Beacon,
+    #[default]
     Nothing,
 }
-impl Default for GridElement {
-    fn default() -> Self {
-        GridElement::Nothing
-    }
-}
 impl Display for GridElement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -43,14 +36,7 @@ fn main() -> Result<()> {
     env_logger::init();
     let input = include_str!("input.txt");
 
-    let sensors = input
-        .lines()
-        .map(|l| {
-            Sensor::from_line(l)
-                .map_err(|e| eyre!("Error while parsing sensor input: {}", e))
-                .unwrap()
-        })
-        .collect_vec();
+    let sensors = parse_sensors(input).map_err(|e| eyre!("{:?}", miette::Report::new(e)))?;
 
     let mut grid: SparseDefaultGrid<GridElement> = SparseDefaultGrid::new();
     for s in &sensors {
@@ -63,50 +49,19 @@ fn main() -> Result<()> {
         println!("Sensor Grid:\n{}", grid);
     }
 
-    let beacon_free_cells = sensors
-        .par_iter()
-        .filter_map(|s| {
-            s.coverage_by_row(ROW_TO_CHECK).map(|r| {
-                r.into_par_iter()
-                    .filter_map(|x| {
-                        let c = GridCoord { x, y: ROW_TO_CHECK };
-                        match grid.at(&c) {
-                            Some(GridElement::Beacon) => None,
-                            Some(_) => Some(c),
-                            None => Some(c),
-                        }
-                    })
-                    .collect::<Vec<_>>()
-            })
-        })
-        .flatten()
-        .collect::<HashSet<_>>();
-
     println!(
         "Coverage for Row {ROW_TO_CHECK}: {}",
-        beacon_free_cells.len(),
+        beacon_free_cells_in_row(&sensors, ROW_TO_CHECK),
     );
 
-    for x in SEARCH_AREA_MIN..=SEARCH_AREA_MAX {
-        if x % 100 == 0 {
-            dbg!(x);
-        }
-        for y in SEARCH_AREA_MIN..=SEARCH_AREA_MAX {
-            let pos = GridCoord { x, y };
-            let mut in_range = false;
-            //dbg!("Position {}", &pos);
-            for s in &sensors {
-                //dbg!("Sensor {}", s);
-                if s.in_coverage_range(&pos) {
-                    //dbg!("In coverage range of sensor {}, cancelling", s);
-                    in_range = true;
-                    break;
-                }
-            }
-            if !in_range {
-                println!("Potential canditate: {}", pos);
-            }
-        }
+    let search_area = 0..=4000000;
+    match find_distress_beacon(&sensors, search_area.clone(), search_area) {
+        Some(pos) => println!(
+            "Distress beacon at {}, tuning frequency: {}",
+            pos,
+            pos.x * TUNING_MULTIPLIER + pos.y
+        ),
+        None => println!("No distress beacon found"),
     }
     Ok(())
 }
@@ -1,8 +1,9 @@
-use std::ops::{Range, RangeInclusive};
+use std::ops::RangeInclusive;
 
-use miette::GraphicalReportHandler;
+use itertools::Itertools;
+
+use miette::{Diagnostic, LabeledSpan, SourceCode};
 use nom::{
-    bytes::complete::tag,
     character::complete::newline,
     combinator::{map, opt},
     error::ParseError,
@@ -11,44 +12,96 @@ use nom::{
 };
 use nom_locate::LocatedSpan;
 use nom_supreme::{
-    error::{BaseErrorKind, ErrorTree, GenericErrorTree},
+    context::ContextError,
+    error::{ErrorTree, GenericErrorTree},
     final_parser::final_parser,
+    tag::{complete::tag, TagError},
+    ParserExt,
 };
 use thiserror::Error;
 
+use util::taxicab::{Diamond, ManhattanGeometry, RotatedPoint};
+
 use crate::grid::GridCoord;
 
 pub type Span<'a> = LocatedSpan<&'a str>;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
-pub enum SensorError {
-    #[error("Failed to parse Sensor Data")]
-    ParseError,
+/// A line of sensor input that could not be parsed.
+///
+/// Contains a label for every alternative the parser tried and every section it was in when it
+/// failed, pointing into the full input.
+#[derive(Error, Debug, Clone)]
+#[error("Bad sensor input on line {line}")]
+pub struct SensorParseError {
+    src: String,
+    line: usize,
+    labels: Vec<LabeledSpan>,
 }
-
-#[derive(Error, Debug, miette::Diagnostic)]
-#[error("Bad Sensor input")]
-struct BadSensorInput {
-    #[source_code]
-    src: &'static str,
-
-    #[label("{kind}")]
-    bad_bit: miette::SourceSpan,
-
-    kind: BaseErrorKind<&'static str, Box<dyn std::error::Error + Send + Sync>>,
+impl SensorParseError {
+    /// Line number of the bad input, starting at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+impl Diagnostic for SensorParseError {
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(
+            "expected `Sensor at x=<int>, y=<int>: closest beacon is at x=<int>, y=<int>`",
+        ))
+    }
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.src)
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(self.labels.iter().cloned()))
+    }
 }
 
-pub trait ManhattanGeometry {
-    type Distance;
-
-    fn taxicab_distance(&self, other: &Self) -> Self::Distance;
+/// Collect labels for every node of the error tree, shifted by `offset` bytes
+fn error_tree_labels(tree: &ErrorTree<Span>, offset: usize, labels: &mut Vec<LabeledSpan>) {
+    match tree {
+        GenericErrorTree::Base { location, kind } => labels.push(LabeledSpan::at_offset(
+            offset + location.location_offset(),
+            kind.to_string(),
+        )),
+        GenericErrorTree::Stack { base, contexts } => {
+            error_tree_labels(base, offset, labels);
+            for (location, context) in contexts {
+                labels.push(LabeledSpan::at_offset(
+                    offset + location.location_offset(),
+                    context.to_string(),
+                ));
+            }
+        }
+        GenericErrorTree::Alt(alternatives) => {
+            for alt in alternatives {
+                error_tree_labels(alt, offset, labels);
+            }
+        }
+    }
 }
-impl ManhattanGeometry for GridCoord {
-    type Distance = usize;
 
-    fn taxicab_distance(&self, other: &Self) -> Self::Distance {
-        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+/// Parse one sensor per line. Blank lines are skipped
+pub fn parse_sensors(input: &str) -> Result<Vec<Sensor>, SensorParseError> {
+    let mut offset = 0;
+    let mut sensors = Vec::new();
+    for (i, line) in input.split_inclusive('\n').enumerate() {
+        let content = line.trim_end();
+        if !content.is_empty() {
+            let sensor = Sensor::_parse_final(content).map_err(|e| {
+                let mut labels = Vec::new();
+                error_tree_labels(&e, offset, &mut labels);
+                SensorParseError {
+                    src: input.to_string(),
+                    line: i + 1,
+                    labels,
+                }
+            })?;
+            sensors.push(sensor);
+        }
+        offset += line.len();
     }
+    Ok(sensors)
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -57,94 +110,69 @@ pub struct Sensor {
     pub nearest_beacon: GridCoord,
 }
 impl Sensor {
-    pub fn from_line(line: &'static str) -> Result<Self, SensorError> {
-        let input = Span::new(line);
-        let res: Result<_, ErrorTree<Span>> =
-            final_parser(Sensor::_parse_line::<ErrorTree<Span>>)(input);
-        let sensor = match res {
-            Ok(sensor) => sensor,
-            Err(e) => {
-                match e {
-                    GenericErrorTree::Base { location, kind } => {
-                        let offset = location.location_offset().into();
-                        let err = BadSensorInput {
-                            src: line,
-                            bad_bit: miette::SourceSpan::new(offset, 0.into()),
-                            kind,
-                        };
-                        let mut s = String::new();
-                        GraphicalReportHandler::new()
-                            .render_report(&mut s, &err)
-                            .unwrap();
-                        println!("{s}");
-                    }
-                    GenericErrorTree::Stack { .. } => todo!("stack"),
-                    GenericErrorTree::Alt(_) => todo!("alt"),
-                };
-                return Err(SensorError::ParseError);
+    pub fn from_line(line: &str) -> Result<Self, SensorParseError> {
+        Sensor::_parse_final(line).map_err(|e| {
+            let mut labels = Vec::new();
+            error_tree_labels(&e, 0, &mut labels);
+            SensorParseError {
+                src: line.to_string(),
+                line: 1,
+                labels,
             }
-        };
-        Ok(sensor)
+        })
     }
     pub fn nearest_beacon_distance(&self) -> usize {
         self.pos.taxicab_distance(&self.nearest_beacon)
     }
 
+    /// All cells that are at most as far away from the sensor as its nearest beacon
+    pub fn coverage(&self) -> Diamond {
+        Diamond::new(self.pos.into(), self.nearest_beacon_distance())
+    }
+
     pub fn coverage_by_row(&self, row: isize) -> Option<RangeInclusive<isize>> {
-        if row > self.pos.y + self.nearest_beacon_distance() as isize
-            || row < self.pos.y - self.nearest_beacon_distance() as isize
-        {
-            return None;
-        }
-        let x_min = self.pos.x
-            - (self.nearest_beacon_distance() as isize - row.abs_diff(self.pos.y) as isize)
-                as isize;
-        let x_max = self.pos.x
-            + (self.nearest_beacon_distance() as isize - row.abs_diff(self.pos.y) as isize)
-                as isize;
-        Some(x_min..=x_max)
+        self.coverage().row(row)
     }
 
     pub fn coverage_by_column(&self, col: isize) -> Option<RangeInclusive<isize>> {
-        if col > self.pos.x + self.nearest_beacon_distance() as isize
-            || col < self.pos.x - self.nearest_beacon_distance() as isize
-        {
-            return None;
-        }
-        let y_min =
-            self.pos.y - (self.nearest_beacon_distance() - col.abs_diff(self.pos.x)) as isize;
-        let y_max =
-            self.pos.y + (self.nearest_beacon_distance() - col.abs_diff(self.pos.x)) as isize;
-        Some(y_min..=y_max)
+        self.coverage().column(col)
     }
 
     pub fn in_coverage_range(&self, pos: &GridCoord) -> bool {
-        match (self.coverage_by_row(pos.y), self.coverage_by_column(pos.x)) {
-            (None, None) => false,
-            (None, Some(_)) => false,
-            (Some(_), None) => false,
-            (Some(row_cov), Some(col_cov)) => row_cov.contains(&pos.x) && col_cov.contains(&pos.y),
-        }
+        self.coverage().contains(&(*pos).into())
     }
 
-    fn _parse_line<'a, E: ParseError<Span<'a>>>(input: Span<'a>) -> IResult<Span<'a>, Sensor, E> {
+    /// All cells just outside of the sensors coverage (at distance + 1), clamped to the given area
+    pub fn perimeter(
+        &self,
+        x_range: RangeInclusive<isize>,
+        y_range: RangeInclusive<isize>,
+    ) -> impl Iterator<Item = GridCoord> {
+        Diamond::new(self.pos.into(), self.nearest_beacon_distance() + 1)
+            .border()
+            .map(GridCoord::from)
+            .filter(move |c| x_range.contains(&c.x) && y_range.contains(&c.y))
+    }
+
+    fn _parse_final(line: &str) -> Result<Sensor, ErrorTree<Span<'_>>> {
+        final_parser(Sensor::_parse_line::<ErrorTree<Span>>)(Span::new(line))
+    }
+
+    fn _parse_line<'a, E>(input: Span<'a>) -> IResult<Span<'a>, Sensor, E>
+    where
+        E: ParseError<Span<'a>>
+            + ContextError<Span<'a>, &'static str>
+            + TagError<Span<'a>, &'static str>,
+    {
         let p = tuple((
             delimited(
                 tag("Sensor at "),
-                separated_pair(
-                    preceded(tag("x="), nom::character::complete::i32),
-                    tag(", "),
-                    preceded(tag("y="), nom::character::complete::i32),
-                ),
+                Sensor::_parse_coord_pair.context("sensor position"),
                 tag(": "),
             ),
             preceded(
                 tag("closest beacon is at "),
-                separated_pair(
-                    preceded(tag("x="), nom::character::complete::i32),
-                    tag(", "),
-                    preceded(tag("y="), nom::character::complete::i32),
-                ),
+                Sensor::_parse_coord_pair.context("beacon position"),
             ),
             opt(newline),
         ));
@@ -160,9 +188,9 @@ impl Sensor {
         })(input)
     }
 
-    fn _parse_coord_pair<'a, E: ParseError<Span<'a>>>(
+    fn _parse_coord_pair<'a, E: ParseError<Span<'a>> + TagError<Span<'a>, &'static str>>(
         input: Span<'a>,
-    ) -> IResult<Span<'a>, (i32, i32)> {
+    ) -> IResult<Span<'a>, (i32, i32), E> {
         separated_pair(
             preceded(tag("x="), nom::character::complete::i32),
             tag(", "),
@@ -170,3 +198,175 @@ impl Sensor {
         )(input)
     }
 }
+
+/// Coverage of all sensors in the given row, as sorted and non-overlapping ranges.
+///
+/// Adjacent ranges are merged as well, so `0..=3` and `4..=5` become `0..=5`
+pub fn merged_coverage_by_row(sensors: &[Sensor], row: isize) -> Vec<RangeInclusive<isize>> {
+    let mut merged: Vec<RangeInclusive<isize>> = Vec::new();
+    for r in sensors
+        .iter()
+        .filter_map(|s| s.coverage_by_row(row))
+        .sorted_by_key(|r| *r.start())
+    {
+        match merged.last_mut() {
+            Some(last) if *r.start() <= *last.end() + 1 => {
+                if r.end() > last.end() {
+                    *last = *last.start()..=*r.end();
+                }
+            }
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+/// Number of cells in the given row that can not contain a beacon
+pub fn beacon_free_cells_in_row(sensors: &[Sensor], row: isize) -> usize {
+    let coverage = merged_coverage_by_row(sensors, row);
+    let covered: usize = coverage
+        .iter()
+        .map(|r| r.end().abs_diff(*r.start()) + 1)
+        .sum();
+    let beacons = sensors
+        .iter()
+        .map(|s| s.nearest_beacon)
+        .filter(|b| b.y == row && coverage.iter().any(|r| r.contains(&b.x)))
+        .unique()
+        .count();
+    covered - beacons
+}
+
+/// Find the only position inside the search area that is not covered by any sensor.
+///
+/// A single uncovered cell has to lie just outside of the coverage of several sensors, so we first
+/// check where the perimeters of two sensors cross. If the cell is in a corner of the search area,
+/// it may only touch one perimeter, in which case we walk the perimeters themselves.
+pub fn find_distress_beacon(
+    sensors: &[Sensor],
+    x_range: RangeInclusive<isize>,
+    y_range: RangeInclusive<isize>,
+) -> Option<GridCoord> {
+    let is_free = |c: &GridCoord| {
+        x_range.contains(&c.x)
+            && y_range.contains(&c.y)
+            && !sensors.iter().any(|s| s.in_coverage_range(c))
+    };
+
+    // Rotated by 45°, the perimeters are squares whose edges lie on the lines u = a and v = b
+    let mut u_edges = Vec::new();
+    let mut v_edges = Vec::new();
+    for s in sensors {
+        let outline = Diamond::new(s.pos.into(), s.nearest_beacon_distance() + 1).rotate();
+        u_edges.extend([*outline.u.start(), *outline.u.end()]);
+        v_edges.extend([*outline.v.start(), *outline.v.end()]);
+    }
+    let candidate = u_edges
+        .iter()
+        .cartesian_product(v_edges.iter())
+        .filter_map(|(&u, &v)| RotatedPoint { u, v }.unrotate())
+        .map(GridCoord::from)
+        .find(&is_free);
+    if candidate.is_some() {
+        return candidate;
+    }
+
+    sensors
+        .iter()
+        .find_map(|s| s.perimeter(x_range.clone(), y_range.clone()).find(&is_free))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+Sensor at x=9, y=16: closest beacon is at x=10, y=16
+Sensor at x=13, y=2: closest beacon is at x=15, y=3
+Sensor at x=12, y=14: closest beacon is at x=10, y=16
+Sensor at x=10, y=20: closest beacon is at x=10, y=16
+Sensor at x=14, y=17: closest beacon is at x=10, y=16
+Sensor at x=8, y=7: closest beacon is at x=2, y=10
+Sensor at x=2, y=0: closest beacon is at x=2, y=10
+Sensor at x=0, y=11: closest beacon is at x=2, y=10
+Sensor at x=20, y=14: closest beacon is at x=25, y=17
+Sensor at x=17, y=20: closest beacon is at x=21, y=22
+Sensor at x=16, y=7: closest beacon is at x=15, y=3
+Sensor at x=14, y=3: closest beacon is at x=15, y=3
+Sensor at x=20, y=1: closest beacon is at x=15, y=3";
+
+    fn example_sensors() -> Vec<Sensor> {
+        parse_sensors(EXAMPLE).unwrap()
+    }
+
+    #[test]
+    fn parse_borrowed_line() {
+        let line = String::from("Sensor at x=2, y=18: closest beacon is at x=-2, y=15");
+        assert_eq!(
+            Sensor::from_line(&line).unwrap(),
+            Sensor {
+                pos: GridCoord { x: 2, y: 18 },
+                nearest_beacon: GridCoord { x: -2, y: 15 },
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_line_and_labels() {
+        let input = "Sensor at x=2, y=18: closest beacon is at x=-2, y=15\n\
+                     Sensor at x=9, y=16: closest beacon is at x=10, z=16\n";
+        let err = parse_sensors(input).unwrap_err();
+        assert_eq!(err.line(), 2);
+        let labels = err.labels().unwrap().collect_vec();
+        // The failed `y=` tag on line 2, plus the "beacon position" context
+        let bad_offset = input.find("z=").unwrap();
+        assert!(labels.iter().any(|l| l.offset() == bad_offset));
+        assert!(labels
+            .iter()
+            .any(|l| l.label().unwrap().contains("beacon position")));
+    }
+
+    #[test]
+    fn parse_error_trailing_input() {
+        let err = Sensor::from_line("Sensor at x=2, y=18: closest beacon is at x=-2, y=15 extra")
+            .unwrap_err();
+        assert_eq!(err.line(), 1);
+        assert!(err.labels().unwrap().count() > 0);
+    }
+
+    #[test]
+    fn merged_row_coverage() {
+        let sensors = example_sensors();
+        assert_eq!(merged_coverage_by_row(&sensors, 10), vec![-2..=24]);
+        assert_eq!(beacon_free_cells_in_row(&sensors, 10), 26);
+    }
+
+    #[test]
+    fn distress_beacon() {
+        let sensors = example_sensors();
+        assert_eq!(
+            find_distress_beacon(&sensors, 0..=20, 0..=20),
+            Some(GridCoord { x: 14, y: 11 })
+        );
+    }
+
+    #[test]
+    fn distress_beacon_in_corner() {
+        // (0, 0) is only on the perimeter of the first sensor, no two perimeters cross there
+        let sensors = [
+            Sensor {
+                pos: GridCoord { x: 5, y: 10 },
+                nearest_beacon: GridCoord { x: 5, y: 24 },
+            },
+            Sensor {
+                pos: GridCoord { x: 10, y: 0 },
+                nearest_beacon: GridCoord { x: 10, y: 5 },
+            },
+        ];
+        assert_eq!(
+            find_distress_beacon(&sensors, 0..=10, 0..=10),
+            Some(GridCoord { x: 0, y: 0 })
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt::Display;
+
+use log::info;
+use thiserror::Error;
+
+use crate::{
+    grid::{DefaultGrid, GridCoord, Origin, SparseDefaultGrid},
+    rock::{Direction, Rock, RockBuilder, RowMask},
+};
+
+pub const CHAMBER_WIDTH: u32 = 7;
+/// Distance between the left wall and a newly spawned rock
+pub const SPAWN_LEFT: u32 = 2;
+/// Empty rows between the highest rock and a newly spawned rock
+pub const SPAWN_GAP: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Error)]
+pub enum ChamberError {
+    #[error(
+        "Chamber width must be between 1 and {} columns, got {0}",
+        RowMask::BITS
+    )]
+    InvalidWidth(u32),
+    #[error(
+        "A rock {0} wide does not fit into the chamber when spawned {1} units from the left wall"
+    )]
+    ShapeTooWide(u32, u32),
+}
+
+#[derive(Clone, Copy, Hash, Debug, PartialEq, Eq, Default)]
+pub enum Element {
+    #[default]
+    Empty,
+    Filled,
+}
+impl Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Element::Empty => ".",
+                Element::Filled => "#",
+            }
+        )
+    }
+}
+
+/// The chamber as a stack of row bitmasks, bottom row first.
+///
+/// Only rows up to the highest settled rock are stored, so the tower height is the number of rows.
+#[derive(Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Chamber {
+    rows: Vec<RowMask>,
+    width: u32,
+    spawn_left: u32,
+    spawn_gap: usize,
+}
+impl Chamber {
+    /// A chamber with the puzzle's layout
+    pub fn new() -> Self {
+        Chamber {
+            rows: Vec::new(),
+            width: CHAMBER_WIDTH,
+            spawn_left: SPAWN_LEFT,
+            spawn_gap: SPAWN_GAP,
+        }
+    }
+
+    /// A chamber `width` columns wide, in which rocks spawn `spawn_left` units away from the left
+    /// wall and `spawn_gap` empty rows above the highest rock
+    pub fn with_layout(
+        width: u32,
+        spawn_left: u32,
+        spawn_gap: usize,
+    ) -> Result<Self, ChamberError> {
+        if width == 0 || width > RowMask::BITS {
+            return Err(ChamberError::InvalidWidth(width));
+        }
+        Ok(Chamber {
+            rows: Vec::new(),
+            width,
+            spawn_left,
+            spawn_gap,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn rows(&self) -> &[RowMask] {
+        &self.rows
+    }
+
+    /// Spawn the next rock from `dropper` at its starting position.
+    ///
+    /// `dropper` only moves on to the following shape if the rock fits into the chamber
+    pub fn spawn(&self, dropper: &mut RockBuilder) -> Result<Rock, ChamberError> {
+        let shape = dropper.peek_shape();
+        if self.spawn_left + shape.width() > self.width {
+            return Err(ChamberError::ShapeTooWide(shape.width(), self.spawn_left));
+        }
+        let rock = shape.place(self.spawn_left, self.height() + self.spawn_gap, self.width);
+        dropper.advance();
+        Ok(rock)
+    }
+
+    pub fn collides(&self, rock: &Rock) -> bool {
+        rock.rows()
+            .iter()
+            .zip(self.rows.iter().skip(rock.bot()))
+            .any(|(r, c)| r & c != 0)
+    }
+
+    /// Move the rock in `direction` if there is space. Returns whether it moved.
+    pub fn try_push(&self, rock: &mut Rock, direction: Direction) -> bool {
+        match rock.pushed(direction, self.width) {
+            Some(moved) if !self.collides(&moved) => {
+                *rock = moved;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Add the rock to the settled rows
+    pub fn settle(&mut self, rock: &Rock) {
+        if rock.top() > self.rows.len() {
+            self.rows.resize(rock.top(), 0);
+        }
+        for (dy, r) in rock.rows().iter().enumerate() {
+            self.rows[rock.bot() + dy] |= r;
+        }
+    }
+
+    /// Let `rock` fall until it comes to rest, alternating between jet pushes and falling
+    pub fn drop_rock(&mut self, mut rock: Rock, jets: &mut impl Iterator<Item = Direction>) {
+        loop {
+            if let Some(direction) = jets.next() {
+                self.try_push(&mut rock, direction);
+            }
+            if !self.try_push(&mut rock, Direction::Down) {
+                self.settle(&rock);
+                return;
+            }
+        }
+    }
+
+    /// Spawn and drop `count` rocks from `dropper`
+    pub fn drop_rocks(
+        &mut self,
+        dropper: &mut RockBuilder,
+        jets: &mut impl Iterator<Item = Direction>,
+        count: usize,
+    ) -> Result<(), ChamberError> {
+        for _ in 0..count {
+            let rock = self.spawn(dropper)?;
+            self.drop_rock(rock, jets);
+            info!("{}", self);
+        }
+        Ok(())
+    }
+
+    /// Render the chamber into a grid for debugging
+    pub fn to_grid(&self) -> SparseDefaultGrid<Element> {
+        let mut grid = SparseDefaultGrid::new(Origin::BotLeft);
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in 0..self.width {
+                let element = if row & (1 << (self.width - 1 - x)) != 0 {
+                    Element::Filled
+                } else {
+                    Element::Empty
+                };
+                grid.set(
+                    &GridCoord {
+                        x: x as isize,
+                        y: y as isize,
+                    },
+                    element,
+                );
+            }
+        }
+        grid
+    }
+}
+impl Default for Chamber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Display for Chamber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_grid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rock::parse_jets;
+
+    const EXAMPLE_JETS: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
+    fn tower_height(
+        mut chamber: Chamber,
+        mut dropper: RockBuilder,
+        jets: &str,
+        count: usize,
+    ) -> usize {
+        let jets = parse_jets(jets).unwrap();
+        let mut jets = jets.into_iter().cycle();
+        chamber.drop_rocks(&mut dropper, &mut jets, count).unwrap();
+        chamber.height()
+    }
+
+    #[test]
+    fn example_tower_height() {
+        assert_eq!(
+            tower_height(Chamber::new(), RockBuilder::new(), EXAMPLE_JETS, 2022),
+            3068
+        );
+    }
+
+    #[test]
+    fn full_width_rocks_stack() {
+        // Nothing can move sideways, so every rock lands on the previous one
+        let chamber = Chamber::with_layout(3, 0, 3).unwrap();
+        let dropper = RockBuilder::from_ascii("###").unwrap();
+        assert_eq!(tower_height(chamber, dropper, "<", 10), 10);
+    }
+
+    #[test]
+    fn dominoes_fill_both_halves() {
+        // The first domino is pushed into columns 0-1 and the second one into columns 2-3, where
+        // it falls down next to the first one
+        let chamber = Chamber::with_layout(4, 2, 3).unwrap();
+        let dropper = RockBuilder::from_ascii("##").unwrap();
+        assert_eq!(tower_height(chamber, dropper, "<<<<>>>>", 2), 1);
+    }
+
+    #[test]
+    fn wide_well() {
+        // A single column rock in a 9-wide well, always pushed right, forms one column
+        let chamber = Chamber::with_layout(9, 6, 3).unwrap();
+        let dropper = RockBuilder::from_ascii("#\n#").unwrap();
+        assert_eq!(tower_height(chamber.clone(), dropper.clone(), ">", 5), 10);
+        let mut chamber = chamber;
+        let mut jets = parse_jets(">").unwrap().into_iter().cycle();
+        chamber
+            .drop_rocks(&mut dropper.clone(), &mut jets, 1)
+            .unwrap();
+        assert_eq!(chamber.rows(), &[0b1, 0b1]);
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        assert_eq!(
+            Chamber::with_layout(33, 2, 3).unwrap_err(),
+            ChamberError::InvalidWidth(33)
+        );
+        let chamber = Chamber::with_layout(5, 2, 3).unwrap();
+        let mut dropper = RockBuilder::new();
+        assert_eq!(
+            chamber.spawn(&mut dropper).unwrap_err(),
+            ChamberError::ShapeTooWide(4, 2)
+        );
+        // The failed spawn doesn't use up the shape
+        assert_eq!(dropper.peek_shape(), &RockBuilder::new().shapes()[0]);
+    }
+
+    #[test]
+    fn padded_shapes_spawn_at_their_rock() {
+        let chamber = Chamber::new();
+        let mut dropper = RockBuilder::from_ascii("..##.\n..##.").unwrap();
+        let rock = chamber.spawn(&mut dropper).unwrap();
+        assert_eq!(rock.rows(), &[0b0011000, 0b0011000]);
+    }
+}
@@ -39,9 +39,9 @@ where
         match self.elements.get(self._index(pos)) {
             Some(e) => {
                 if T::default() == *e {
-                    return None;
+                    None
                 } else {
-                    return Some(e);
+                    Some(e)
                 }
             }
             None => None,
@@ -20,7 +20,7 @@ where
     }
 
     fn set(&mut self, pos: &GridCoord, element: T) -> Option<T> {
-        let old = self.elements.remove(&pos).or(Some(self.default));
+        let old = self.elements.remove(pos).or(Some(self.default));
         self.elements.insert(*pos, element);
         old
     }
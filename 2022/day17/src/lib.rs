@@ -1,2 +1,3 @@
+pub mod chamber;
 pub mod grid;
 pub mod rock;
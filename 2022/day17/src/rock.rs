@@ -1,82 +1,192 @@
-use itertools::Itertools;
+use thiserror::Error;
 
-use crate::grid::{DefaultGrid, GridCoord};
+use crate::grid::GridCoord;
+
+/// One row of the chamber or of a rock, one bit per column.
+///
+/// The most significant used bit is the leftmost column, so a mask reads the same way as the
+/// puzzle drawing: `0b0011110` is the horizontal rock two units away from the left wall of a
+/// 7-wide chamber.
+pub type RowMask = u32;
+
+/// The five rocks from the puzzle, in falling order
+pub const DEFAULT_SHAPES: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Error)]
+pub enum ShapeError {
+    #[error("Shape {0} has no filled cells")]
+    Empty(usize),
+    #[error("Invalid character `{1}` in shape {0}")]
+    InvalidChar(usize, char),
+    #[error("Shape {0} is wider than {} columns", RowMask::BITS)]
+    TooWide(usize),
+    #[error("No shapes given")]
+    NoShapes,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Error)]
+#[error("Invalid jet `{1}` at position {0}")]
+pub struct JetError(usize, char);
+
+/// Parse a jet pattern made of `<` and `>`
+pub fn parse_jets(input: &str) -> Result<Vec<Direction>, JetError> {
+    input
+        .trim()
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            '<' => Ok(Direction::Left),
+            '>' => Ok(Direction::Right),
+            e => Err(JetError(i, e)),
+        })
+        .collect()
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Shape {
+    /// Rows from bottom to top, right-aligned at bit 0
+    rows: Vec<RowMask>,
+    width: u32,
+}
+impl Shape {
+    pub fn new(rows: Vec<RowMask>, width: u32) -> Self {
+        Shape { rows, width }
+    }
+
+    /// Parse a single shape drawn with `#` (rock) and `.` (air), top row first.
+    ///
+    /// `index` is only used for error messages. Lines may be shorter than the widest one, the
+    /// missing cells are treated as air. Rows and columns of only air around the rock are dropped.
+    pub fn from_ascii(drawing: &str, index: usize) -> Result<Self, ShapeError> {
+        let lines: Vec<&str> = drawing.lines().map(|l| l.trim_end()).collect();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        if width > RowMask::BITS as usize {
+            return Err(ShapeError::TooWide(index));
+        }
+        let mut rows = Vec::with_capacity(lines.len());
+        for line in lines.iter().rev() {
+            let mut row: RowMask = 0;
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => row |= 1 << (width - 1 - x),
+                    '.' => (),
+                    e => return Err(ShapeError::InvalidChar(index, e)),
+                }
+            }
+            rows.push(row);
+        }
+        let (Some(bot), Some(top)) = (
+            rows.iter().position(|r| *r != 0),
+            rows.iter().rposition(|r| *r != 0),
+        ) else {
+            return Err(ShapeError::Empty(index));
+        };
+        rows.truncate(top + 1);
+        rows.drain(..bot);
+        // Shift blank columns on the right out, those on the left are cut off by the width
+        let filled = rows.iter().fold(0, |acc, r| acc | r);
+        let right = filled.trailing_zeros();
+        for row in rows.iter_mut() {
+            *row >>= right;
+        }
+        Ok(Shape {
+            rows,
+            width: RowMask::BITS - (filled >> right).leading_zeros(),
+        })
+    }
+
+    pub fn rows(&self) -> &[RowMask] {
+        &self.rows
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+    /// Place the shape in a chamber `chamber_width` columns wide, with its left edge in column `left`
+    /// and its bottom row at `bot`.
+    ///
+    /// The caller has to make sure the shape fits, see [`crate::chamber::Chamber::spawn`]
+    pub fn place(&self, left: u32, bot: usize, chamber_width: u32) -> Rock {
+        let shift = chamber_width - left - self.width;
+        Rock {
+            rows: self.rows.iter().map(|r| r << shift).collect(),
+            bot,
+        }
+    }
+}
 
 #[derive(Clone, Hash, Debug, PartialEq, Eq)]
 pub struct RockBuilder {
     counter: usize,
-    shapes: Vec<Rock>,
+    shapes: Vec<Shape>,
 }
 impl RockBuilder {
     pub fn new() -> Self {
-        RockBuilder {
-            counter: 0,
-            // Shape origin is bottom-left
-            shapes: vec![
-                Rock {
-                    bits: vec![
-                        // Wide
-                        GridCoord { x: 0, y: 0 },
-                        GridCoord { x: 1, y: 0 },
-                        GridCoord { x: 2, y: 0 },
-                        GridCoord { x: 3, y: 0 },
-                    ],
-                },
-                Rock {
-                    bits: vec![
-                        // +
-                        GridCoord { x: 1, y: 0 },
-                        GridCoord { x: 0, y: 1 },
-                        GridCoord { x: 1, y: 1 },
-                        GridCoord { x: 2, y: 1 },
-                        GridCoord { x: 1, y: 2 },
-                    ],
-                },
-                Rock {
-                    bits: vec![
-                        // Corner, inverse L
-                        GridCoord { x: 2, y: 2 },
-                        GridCoord { x: 2, y: 1 },
-                        GridCoord { x: 2, y: 0 },
-                        GridCoord { x: 1, y: 0 },
-                        GridCoord { x: 0, y: 0 },
-                    ],
-                },
-                Rock {
-                    bits: vec![
-                        // High
-                        GridCoord { x: 0, y: 0 },
-                        GridCoord { x: 0, y: 1 },
-                        GridCoord { x: 0, y: 2 },
-                        GridCoord { x: 0, y: 3 },
-                    ],
-                },
-                Rock {
-                    bits: vec![
-                        // Square
-                        GridCoord { x: 0, y: 0 },
-                        GridCoord { x: 0, y: 1 },
-                        GridCoord { x: 1, y: 1 },
-                        GridCoord { x: 1, y: 0 },
-                    ],
-                },
-            ],
+        Self::from_ascii(DEFAULT_SHAPES).expect("Default shapes are valid")
+    }
+
+    /// Load shapes from an ASCII-art description, with shapes separated by blank lines
+    /// (see [`DEFAULT_SHAPES`]). Shapes are dropped in the order they are listed, there has to be
+    /// at least one.
+    pub fn from_ascii(description: &str) -> Result<Self, ShapeError> {
+        let normalized = description.replace("\r\n", "\n");
+        let shapes = normalized
+            .split("\n\n")
+            .map(|s| s.trim_matches('\n'))
+            .filter(|s| !s.trim().is_empty())
+            .enumerate()
+            .map(|(i, s)| Shape::from_ascii(s, i))
+            .collect::<Result<Vec<_>, _>>()?;
+        if shapes.is_empty() {
+            return Err(ShapeError::NoShapes);
         }
+        Ok(RockBuilder { counter: 0, shapes })
     }
-    pub fn drop_at_pos(&mut self, pos: &GridCoord) -> Rock {
-        let r = Rock {
-            bits: self.shapes[self.counter % self.shapes.len()]
-                .bits
-                .iter()
-                .cloned()
-                .map(|piece| GridCoord {
-                    x: piece.x + pos.x,
-                    y: piece.y + pos.y,
-                })
-                .collect_vec(),
-        };
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// The shape that will be dropped next, without moving on to the one after it
+    pub fn peek_shape(&self) -> &Shape {
+        &self.shapes[self.counter % self.shapes.len()]
+    }
+
+    /// Move on to the next shape in the cycle
+    pub fn advance(&mut self) {
         self.counter += 1;
-        r
+    }
+
+    /// Return the next shape to drop, cycling through all shapes
+    pub fn next_shape(&mut self) -> &Shape {
+        let index = self.counter % self.shapes.len();
+        self.advance();
+        &self.shapes[index]
+    }
+}
+impl Default for RockBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -84,58 +194,129 @@ impl RockBuilder {
 pub enum Direction {
     Left,
     Right,
-    Up,
     Down,
 }
 
 #[derive(Clone, Hash, Debug, PartialEq, Eq)]
 pub struct Rock {
-    bits: Vec<GridCoord>,
+    /// Rows from bottom to top, already shifted into their chamber columns
+    rows: Vec<RowMask>,
+    bot: usize,
 }
 impl Rock {
-    pub fn push(&mut self, direction: Direction) {
-        for b in &mut self.bits {
-            match direction {
-                Direction::Left => b.x -= 1,
-                Direction::Right => b.x += 1,
-                Direction::Up => b.y += 1,
-                Direction::Down => b.y -= 1,
+    /// Returns the rock moved by one unit, or `None` if it would leave a chamber of the given width.
+    ///
+    /// Collisions with settled rocks are checked by the chamber.
+    pub fn pushed(&self, direction: Direction, chamber_width: u32) -> Option<Rock> {
+        let wall = match direction {
+            Direction::Left => 1 << (chamber_width - 1),
+            Direction::Right => 1,
+            Direction::Down => {
+                return self.bot.checked_sub(1).map(|bot| Rock {
+                    rows: self.rows.clone(),
+                    bot,
+                });
             }
+        };
+        if self.rows.iter().any(|r| r & wall != 0) {
+            return None;
         }
+        Some(Rock {
+            rows: self
+                .rows
+                .iter()
+                .map(|r| match direction {
+                    Direction::Left => r << 1,
+                    _ => r >> 1,
+                })
+                .collect(),
+            bot: self.bot,
+        })
     }
-    pub fn push_back(&mut self, direction: Direction) {
-        match direction {
-            Direction::Left => self.push(Direction::Right),
-            Direction::Right => self.push(Direction::Left),
-            Direction::Up => self.push(Direction::Down),
-            Direction::Down => self.push(Direction::Up),
-        }
+    pub fn rows(&self) -> &[RowMask] {
+        &self.rows
     }
-    pub fn bits(&self) -> impl Iterator<Item = &GridCoord> {
-        self.bits.iter()
+    pub fn bot(&self) -> usize {
+        self.bot
     }
-
-    pub fn left(&self) -> isize {
-        self.bits.iter().map(|b| b.x).min().unwrap_or(0)
+    /// Row above the topmost piece of the rock
+    pub fn top(&self) -> usize {
+        self.bot + self.rows.len()
     }
-    pub fn right(&self) -> isize {
-        self.bits.iter().map(|b| b.x).max().unwrap_or(0)
+    /// Coordinates of all pieces, with x counted from the left wall
+    pub fn bits(&self, chamber_width: u32) -> impl Iterator<Item = GridCoord> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(dy, row)| {
+            (0..chamber_width)
+                .filter(move |x| row & (1 << (chamber_width - 1 - x)) != 0)
+                .map(move |x| GridCoord {
+                    x: x as isize,
+                    y: (self.bot + dy) as isize,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_shapes_from_ascii() {
+        let builder = RockBuilder::new();
+        let shapes = builder.shapes();
+        assert_eq!(shapes.len(), 5);
+        assert_eq!(shapes[0], Shape::new(vec![0b1111], 4));
+        assert_eq!(shapes[1], Shape::new(vec![0b010, 0b111, 0b010], 3));
+        assert_eq!(shapes[2], Shape::new(vec![0b111, 0b001, 0b001], 3));
+        assert_eq!(shapes[3], Shape::new(vec![0b1; 4], 1));
+        assert_eq!(shapes[4], Shape::new(vec![0b11, 0b11], 2));
     }
-    pub fn top(&self) -> isize {
-        self.bits.iter().map(|b| b.y).max().unwrap_or(0)
+
+    #[test]
+    fn invalid_shapes() {
+        assert_eq!(
+            RockBuilder::from_ascii("##\n\n#x").unwrap_err(),
+            ShapeError::InvalidChar(1, 'x')
+        );
+        assert_eq!(
+            RockBuilder::from_ascii("...").unwrap_err(),
+            ShapeError::Empty(0)
+        );
+        assert_eq!(
+            RockBuilder::from_ascii(&"#".repeat(33)).unwrap_err(),
+            ShapeError::TooWide(0)
+        );
+        assert_eq!(
+            RockBuilder::from_ascii("").unwrap_err(),
+            ShapeError::NoShapes
+        );
+        assert_eq!(
+            RockBuilder::from_ascii(" \n\n\n").unwrap_err(),
+            ShapeError::NoShapes
+        );
     }
-    pub fn bot(&self) -> isize {
-        self.bits.iter().map(|b| b.y).min().unwrap_or(0)
+
+    #[test]
+    fn blank_rows_are_trimmed() {
+        assert_eq!(
+            Shape::from_ascii("..\n##\n.#\n..", 0).unwrap(),
+            Shape::new(vec![0b01, 0b11], 2)
+        );
     }
-    pub fn collides<T>(&self, grid: &dyn DefaultGrid<T>) -> bool
-    where
-        T: Default,
-    {
-        for b in &self.bits {
-            if grid.at_non_default(b).is_some() {
-                return true;
-            }
-        }
-        return false;
+
+    #[test]
+    fn blank_columns_are_trimmed() {
+        assert_eq!(
+            Shape::from_ascii("..#..\n.##\n", 0).unwrap(),
+            Shape::new(vec![0b11, 0b01], 2)
+        );
+        assert_eq!(
+            Shape::from_ascii("...#\n...#", 0).unwrap(),
+            Shape::new(vec![0b1, 0b1], 1)
+        );
+        assert_eq!(
+            Shape::from_ascii("#...", 0).unwrap(),
+            Shape::new(vec![0b1], 1)
+        );
     }
 }
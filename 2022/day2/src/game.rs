@@ -0,0 +1,135 @@
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+pub enum GameError {
+    #[error("A game needs an odd number of at least 3 shapes, got {0}")]
+    InvalidSize(usize),
+}
+
+/// A shape, identified by its position in the cycle. Its score is the position plus 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Shape(pub usize);
+impl Shape {
+    pub fn score(&self) -> u64 {
+        self.0 as u64 + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Lose,
+    Draw,
+    Win,
+}
+impl Outcome {
+    pub fn score(&self) -> u64 {
+        match self {
+            Outcome::Lose => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6,
+        }
+    }
+}
+
+/// A single round, from our point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Round {
+    pub opponent: Shape,
+    pub me: Shape,
+}
+
+/// Rock-paper-scissors with any odd number of shapes arranged in a cycle.
+///
+/// Every shape beats the `(n - 1) / 2` shapes before it in the cycle and loses to the ones after
+/// it, so each pair of different shapes has exactly one winner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Game {
+    size: usize,
+}
+impl Game {
+    pub fn new(size: usize) -> Result<Self, GameError> {
+        if size < 3 || size & 1 == 0 {
+            return Err(GameError::InvalidSize(size));
+        }
+        Ok(Game { size })
+    }
+
+    /// Rock, Paper, Scissors
+    pub fn rock_paper_scissors() -> Self {
+        Game { size: 3 }
+    }
+
+    /// Rock, Spock, Paper, Lizard, Scissors
+    pub fn rock_paper_scissors_lizard_spock() -> Self {
+        Game { size: 5 }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn shape(&self, index: usize) -> Option<Shape> {
+        (index < self.size).then_some(Shape(index))
+    }
+
+    /// How a round ends for `me`
+    pub fn outcome(&self, me: Shape, opponent: Shape) -> Outcome {
+        match (me.0 + self.size - opponent.0) % self.size {
+            0 => Outcome::Draw,
+            d if d <= self.size / 2 => Outcome::Win,
+            _ => Outcome::Lose,
+        }
+    }
+
+    /// A shape that ends the round against `opponent` with `outcome`. If several shapes
+    /// qualify, the one closest to `opponent` in the cycle is picked
+    pub fn response(&self, opponent: Shape, outcome: Outcome) -> Shape {
+        Shape(match outcome {
+            Outcome::Lose => (opponent.0 + self.size - 1) % self.size,
+            Outcome::Draw => opponent.0,
+            Outcome::Win => (opponent.0 + 1) % self.size,
+        })
+    }
+
+    /// Our score for a round: the shape we played plus the outcome
+    pub fn score(&self, round: &Round) -> u64 {
+        round.me.score() + self.outcome(round.me, round.opponent).score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cyclic_dominance() {
+        for game in [
+            Game::rock_paper_scissors(),
+            Game::new(5).unwrap(),
+            Game::new(7).unwrap(),
+        ] {
+            for a in 0..game.size() {
+                let wins = (0..game.size())
+                    .filter(|b| game.outcome(Shape(a), Shape(*b)) == Outcome::Win)
+                    .count();
+                assert_eq!(wins, game.size() / 2);
+                for outcome in [Outcome::Lose, Outcome::Draw, Outcome::Win] {
+                    let me = game.response(Shape(a), outcome);
+                    assert_eq!(game.outcome(me, Shape(a)), outcome);
+                }
+            }
+        }
+        assert_eq!(Game::new(4), Err(GameError::InvalidSize(4)));
+    }
+
+    #[test]
+    fn lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let (rock, spock, paper, lizard, scissors) =
+            (Shape(0), Shape(1), Shape(2), Shape(3), Shape(4));
+        assert_eq!(game.outcome(spock, scissors), Outcome::Win);
+        assert_eq!(game.outcome(lizard, spock), Outcome::Win);
+        assert_eq!(game.outcome(paper, lizard), Outcome::Lose);
+        assert_eq!(game.outcome(rock, lizard), Outcome::Win);
+    }
+}
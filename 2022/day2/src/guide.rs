@@ -0,0 +1,163 @@
+use thiserror::Error;
+
+use crate::game::{Game, Outcome, Round};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+#[error("Line {line}: {kind}")]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Error)]
+pub enum ParseErrorKind {
+    #[error("expected two columns separated by a space")]
+    MissingColumn,
+    #[error("unknown opponent shape '{0}'")]
+    UnknownShape(char),
+    #[error("unknown response '{0}'")]
+    UnknownResponse(char),
+}
+
+/// What the second column of the guide means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpretation {
+    /// The shape to play, the first guess from part 1
+    Shape,
+    /// Whether to lose, draw or win, as the elf explains in part 2
+    Outcome,
+}
+
+/// The letters used for the first shape and first response. Later ones follow in alphabetical
+/// order, so the puzzle uses `A B C` for shapes and `X Y Z` for responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Notation {
+    pub opponent: char,
+    pub response: char,
+}
+impl Default for Notation {
+    fn default() -> Self {
+        Notation {
+            opponent: 'A',
+            response: 'X',
+        }
+    }
+}
+
+/// Parse a strategy guide into the rounds it says to play. Blank lines are skipped
+pub fn parse_guide(
+    input: &str,
+    game: &Game,
+    notation: Notation,
+    interpretation: Interpretation,
+) -> Result<Vec<Round>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            parse_round(l, game, notation, interpretation)
+                .map_err(|kind| ParseError { line: i + 1, kind })
+        })
+        .collect()
+}
+
+fn parse_round(
+    line: &str,
+    game: &Game,
+    notation: Notation,
+    interpretation: Interpretation,
+) -> Result<Round, ParseErrorKind> {
+    let mut columns = line.split_whitespace().map(|c| {
+        let mut chars = c.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(ParseErrorKind::MissingColumn),
+        }
+    });
+    let (Some(opponent), Some(response), None) = (columns.next(), columns.next(), columns.next())
+    else {
+        return Err(ParseErrorKind::MissingColumn);
+    };
+    let (opponent, response) = (opponent?, response?);
+
+    let opponent = letter_index(opponent, notation.opponent)
+        .and_then(|i| game.shape(i))
+        .ok_or(ParseErrorKind::UnknownShape(opponent))?;
+    let index = letter_index(response, notation.response);
+    let me = match interpretation {
+        Interpretation::Shape => index.and_then(|i| game.shape(i)),
+        Interpretation::Outcome => index
+            .and_then(|i| [Outcome::Lose, Outcome::Draw, Outcome::Win].get(i).copied())
+            .map(|outcome| game.response(opponent, outcome)),
+    }
+    .ok_or(ParseErrorKind::UnknownResponse(response))?;
+    Ok(Round { opponent, me })
+}
+
+/// Position of `c` in the alphabet, counting from `first`
+fn letter_index(c: char, first: char) -> Option<usize> {
+    (c as u32).checked_sub(first as u32).map(|i| i as usize)
+}
+
+/// Total score for following the guide
+pub fn total_score(game: &Game, rounds: &[Round]) -> u64 {
+    rounds.iter().map(|r| game.score(r)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Shape;
+
+    const EXAMPLE: &str = "A Y\nB X\nC Z\n";
+
+    #[test]
+    fn example() {
+        let game = Game::rock_paper_scissors();
+        let shapes = parse_guide(EXAMPLE, &game, Notation::default(), Interpretation::Shape);
+        assert_eq!(total_score(&game, &shapes.unwrap()), 15);
+        let outcomes = parse_guide(EXAMPLE, &game, Notation::default(), Interpretation::Outcome);
+        assert_eq!(total_score(&game, &outcomes.unwrap()), 12);
+    }
+
+    #[test]
+    fn errors() {
+        let game = Game::rock_paper_scissors();
+        let parse = |input| parse_guide(input, &game, Notation::default(), Interpretation::Shape);
+        assert_eq!(
+            parse("A Y\n\nD X\n"),
+            Err(ParseError {
+                line: 3,
+                kind: ParseErrorKind::UnknownShape('D')
+            })
+        );
+        assert_eq!(
+            parse("A W").unwrap_err().kind,
+            ParseErrorKind::UnknownResponse('W')
+        );
+        assert_eq!(parse("AY").unwrap_err().kind, ParseErrorKind::MissingColumn);
+        assert_eq!(
+            parse("A Y Z").unwrap_err().kind,
+            ParseErrorKind::MissingColumn
+        );
+
+        // Larger games accept more letters, but there are still only three outcomes
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let outcome = parse_guide("E [", &game, Notation::default(), Interpretation::Outcome);
+        assert_eq!(
+            outcome.unwrap_err().kind,
+            ParseErrorKind::UnknownResponse('[')
+        );
+        let spock = parse_guide(
+            "E W",
+            &game,
+            Notation {
+                opponent: 'A',
+                response: 'V',
+            },
+            Interpretation::Shape,
+        );
+        assert_eq!(spock.unwrap()[0].me, Shape(1));
+    }
+}
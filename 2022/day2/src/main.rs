@@ -1,59 +1,29 @@
-use rayon::prelude::*;
-use tokio::fs::read_to_string;
+use std::fs::read_to_string;
 
-enum Play {
-    Rock,
-    Paper,
-    Scissors,
-}
-impl From<char> for Play {
-    fn from(c: char) -> Self {
-        match c {
-            'A' => Play::Rock,
-            'B' => Play::Paper,
-            'C' => Play::Scissors,
-            _ => panic!(),
-        }
-    }
-}
-enum Goal {
-    Lose,
-    Tie,
-    Win,
-}
-impl From<char> for Goal {
-    fn from(c: char) -> Self {
-        match c {
-            'X' => Goal::Lose,
-            'Y' => Goal::Tie,
-            'Z' => Goal::Win,
-            _ => panic!(),
-        }
-    }
-}
+use color_eyre::{eyre::Context, Result};
+use day2::{
+    game::Game,
+    guide::{parse_guide, total_score, Interpretation, Notation},
+};
 
-#[tokio::main]
-async fn main() {
-    let input = read_to_string("input.txt").await.unwrap();
+fn main() -> Result<()> {
+    color_eyre::install()?;
 
-    let score: usize = input
-        .par_lines()
-        .map(|l| {
-            let play: Play = l.chars().next().unwrap().into();
-            let goal: Goal = l.chars().nth(2).unwrap().into();
-            match (play, goal) {
-                (Play::Rock, Goal::Lose) => 3,     // Scissors
-                (Play::Rock, Goal::Tie) => 4,      // Rock
-                (Play::Rock, Goal::Win) => 8,      // Paper
-                (Play::Paper, Goal::Lose) => 1,    // Rock
-                (Play::Paper, Goal::Tie) => 5,     // Paper
-                (Play::Paper, Goal::Win) => 9,     // Scissors
-                (Play::Scissors, Goal::Lose) => 2, // Paper
-                (Play::Scissors, Goal::Tie) => 6,  // Scissors
-                (Play::Scissors, Goal::Win) => 7,  // Rock
-            }
-        })
-        .sum();
+    let input = read_to_string("input.txt").wrap_err("Reading input.txt")?;
+    let game = Game::rock_paper_scissors();
+
+    for (name, interpretation) in [
+        ("shapes", Interpretation::Shape),
+        ("outcomes", Interpretation::Outcome),
+    ] {
+        let rounds = parse_guide(&input, &game, Notation::default(), interpretation)
+            .wrap_err("Parsing the strategy guide")?;
+        println!(
+            "Total score when reading the guide as {}: {}",
+            name,
+            total_score(&game, &rounds)
+        );
+    }
 
-    println!("Total score: {}", score);
+    Ok(())
 }
@@ -1,9 +1,9 @@
-use std::{collections::HashSet, fs::read_to_string};
+use std::fs::read_to_string;
 
-use day3::items::Item;
+use day3::items::{ItemError, ItemSet};
 use rayon::prelude::*;
 
-use color_eyre::{eyre::eyre, Result};
+use color_eyre::{eyre::Context, Result};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -11,27 +11,21 @@ async fn main() -> Result<()> {
 
     let input = read_to_string("input.txt")?;
 
-    let prio_sum: Result<u64, _> = input
+    let prio_sum: Result<u64> = input
         .par_lines()
         .map(|l| {
-            let (left, right) = l.split_at(l.len() / 2);
-            let items_l: HashSet<Item> = left.par_chars().map(|c| c.into()).collect();
-            let items_r: HashSet<Item> = right.par_chars().map(|c| c.into()).collect();
-
-            let mut in_both = &items_l & &items_r;
-
-            match in_both.len() {
-                1 => Ok(in_both.drain().next().unwrap().prio as u64),
-                2.. => Err(eyre!("More than 1 duplicate item in compartment!")),
-                _ => Err(eyre!("No duplicate item in compartment!")),
+            // Only ASCII letters are items, which also makes the midpoint a char boundary
+            if let Some(c) = l.chars().find(|c| !c.is_ascii_alphabetic()) {
+                return Err(ItemError::NotALetter(c).into());
             }
+            let (left, right) = l.split_at(l.len() / 2);
+            let compartments = [left.parse::<ItemSet>()?, right.parse::<ItemSet>()?];
+            let in_both = ItemSet::common(compartments)
+                .single()
+                .wrap_err_with(|| format!("Finding the duplicate item in rucksack {}", l))?;
+            Ok(in_both.prio as u64)
         })
         .sum();
-    match prio_sum {
-        Ok(s) => {
-            println!("Sum of all item priorities: {}", s);
-            Ok(())
-        }
-        Err(e) => Err(e),
-    }
+    println!("Sum of all item priorities: {}", prio_sum?);
+    Ok(())
 }
@@ -1,38 +1,32 @@
-use std::{collections::HashSet, fs::read_to_string};
+use std::fs::read_to_string;
 
-use day3::items::Item;
+use day3::items::ItemSet;
 use rayon::prelude::*;
 
-use color_eyre::{eyre::eyre, Result};
+use color_eyre::{eyre::Context, Result};
+
+const GROUP_SIZE: usize = 3;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
 
     let input = read_to_string("input.txt")?;
-    let lines: Vec<_> = input.par_lines().collect();
-
-    let prio_sum: Result<u64, _> = lines
-        .par_chunks(3)
-        .map(|group| {
-            let items_0: HashSet<Item> = group[0].par_chars().map(|c| c.into()).collect();
-            let items_1: HashSet<Item> = group[1].par_chars().map(|c| c.into()).collect();
-            let items_2: HashSet<Item> = group[2].par_chars().map(|c| c.into()).collect();
-
-            let mut combined = &(&items_0 & &items_1) & &items_2;
+    let rucksacks = input
+        .par_lines()
+        .map(|l| l.parse::<ItemSet>())
+        .collect::<Result<Vec<_>, _>>()?;
 
-            match combined.len() {
-                1 => Ok(combined.drain().next().unwrap().prio as u64),
-                2.. => Err(eyre!("More than 1 item shared across the group!")),
-                _ => Err(eyre!("No shared item found for group!")),
-            }
+    let prio_sum: Result<u64> = rucksacks
+        .par_chunks(GROUP_SIZE)
+        .enumerate()
+        .map(|(i, group)| {
+            let badge = ItemSet::common(group.iter().copied())
+                .single()
+                .wrap_err_with(|| format!("Finding the badge of group {}", i + 1))?;
+            Ok(badge.prio as u64)
         })
         .sum();
-    match prio_sum {
-        Ok(s) => {
-            println!("Sum of all item priorities: {}", s);
-            Ok(())
-        }
-        Err(e) => Err(e),
-    }
+    println!("Sum of all item priorities: {}", prio_sum?);
+    Ok(())
 }
@@ -1,65 +1,186 @@
-#[derive(Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Hash)]
+use std::{
+    fmt::Display,
+    ops::{BitAnd, BitOr},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Error)]
+pub enum ItemError {
+    #[error("'{0}' is not an item, items are letters")]
+    NotALetter(char),
+    #[error("Expected exactly one shared item, found none")]
+    NoneShared,
+    #[error("Expected exactly one shared item, found {0}")]
+    MultipleShared(ItemSet),
+}
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
 #[non_exhaustive]
 pub struct Item {
     pub prio: u8,
 }
+impl Item {
+    /// The item with the given priority, `a-z` are 1-26 and `A-Z` are 27-52
+    pub fn from_prio(prio: u8) -> Option<Self> {
+        (1..=52).contains(&prio).then_some(Item { prio })
+    }
+
+    pub fn as_char(&self) -> char {
+        match self.prio {
+            1..=26 => (b'a' + self.prio - 1) as char,
+            _ => (b'A' + self.prio - 27) as char,
+        }
+    }
+}
+impl TryFrom<char> for Item {
+    type Error = ItemError;
 
-impl From<char> for Item {
-    fn from(c: char) -> Self {
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'a' => Item { prio: 1 },
-            'b' => Item { prio: 2 },
-            'c' => Item { prio: 3 },
-            'd' => Item { prio: 4 },
-            'e' => Item { prio: 5 },
-            'f' => Item { prio: 6 },
-            'g' => Item { prio: 7 },
-            'h' => Item { prio: 8 },
-            'i' => Item { prio: 9 },
-            'j' => Item { prio: 10 },
-            'k' => Item { prio: 11 },
-            'l' => Item { prio: 12 },
-            'm' => Item { prio: 13 },
-            'n' => Item { prio: 14 },
-            'o' => Item { prio: 15 },
-            'p' => Item { prio: 16 },
-            'q' => Item { prio: 17 },
-            'r' => Item { prio: 18 },
-            's' => Item { prio: 19 },
-            't' => Item { prio: 20 },
-            'u' => Item { prio: 21 },
-            'v' => Item { prio: 22 },
-            'w' => Item { prio: 23 },
-            'x' => Item { prio: 24 },
-            'y' => Item { prio: 25 },
-            'z' => Item { prio: 26 },
-            'A' => Item { prio: 27 },
-            'B' => Item { prio: 28 },
-            'C' => Item { prio: 29 },
-            'D' => Item { prio: 30 },
-            'E' => Item { prio: 31 },
-            'F' => Item { prio: 32 },
-            'G' => Item { prio: 33 },
-            'H' => Item { prio: 34 },
-            'I' => Item { prio: 35 },
-            'J' => Item { prio: 36 },
-            'K' => Item { prio: 37 },
-            'L' => Item { prio: 38 },
-            'M' => Item { prio: 39 },
-            'N' => Item { prio: 40 },
-            'O' => Item { prio: 41 },
-            'P' => Item { prio: 42 },
-            'Q' => Item { prio: 43 },
-            'R' => Item { prio: 44 },
-            'S' => Item { prio: 45 },
-            'T' => Item { prio: 46 },
-            'U' => Item { prio: 47 },
-            'V' => Item { prio: 48 },
-            'W' => Item { prio: 49 },
-            'X' => Item { prio: 50 },
-            'Y' => Item { prio: 51 },
-            'Z' => Item { prio: 52 },
-            _ => panic!(),
+            'a'..='z' => Ok(Item {
+                prio: c as u8 - b'a' + 1,
+            }),
+            'A'..='Z' => Ok(Item {
+                prio: c as u8 - b'A' + 27,
+            }),
+            _ => Err(ItemError::NotALetter(c)),
+        }
+    }
+}
+
+/// A set of items, stored as a bitmask with one bit per priority
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct ItemSet(u64);
+impl ItemSet {
+    pub fn new() -> Self {
+        ItemSet(0)
+    }
+
+    pub fn insert(&mut self, item: Item) {
+        self.0 |= 1 << item.prio;
+    }
+
+    pub fn contains(&self, item: Item) -> bool {
+        self.0 & (1 << item.prio) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn intersection(&self, other: &ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    pub fn union(&self, other: &ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    /// Items contained in every set of the group. An empty group shares nothing
+    pub fn common(group: impl IntoIterator<Item = ItemSet>) -> ItemSet {
+        group
+            .into_iter()
+            .reduce(|acc, s| acc & s)
+            .unwrap_or_default()
+    }
+
+    /// The only item in the set
+    pub fn single(&self) -> Result<Item, ItemError> {
+        match self.len() {
+            0 => Err(ItemError::NoneShared),
+            1 => Ok(Item {
+                prio: self.0.trailing_zeros() as u8,
+            }),
+            _ => Err(ItemError::MultipleShared(*self)),
+        }
+    }
+
+    /// Priorities of all items in the set, lowest first
+    pub fn priorities(&self) -> impl Iterator<Item = u8> + '_ {
+        (1..=52).filter(|p| self.0 & (1 << p) != 0)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = Item> + '_ {
+        self.priorities().map(|prio| Item { prio })
+    }
+}
+impl FromStr for ItemSet {
+    type Err = ItemError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().map(Item::try_from).collect()
+    }
+}
+impl FromIterator<Item> for ItemSet {
+    fn from_iter<T: IntoIterator<Item = Item>>(iter: T) -> Self {
+        let mut set = ItemSet::new();
+        for item in iter {
+            set.insert(item);
+        }
+        set
+    }
+}
+impl BitAnd for ItemSet {
+    type Output = ItemSet;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.intersection(&rhs)
+    }
+}
+impl BitOr for ItemSet {
+    type Output = ItemSet;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(&rhs)
+    }
+}
+impl Display for ItemSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for item in self.items() {
+            write!(f, "{}", item.as_char())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priorities() {
+        assert_eq!(Item::try_from('a').unwrap().prio, 1);
+        assert_eq!(Item::try_from('Z').unwrap().prio, 52);
+        assert_eq!(Item::try_from('1'), Err(ItemError::NotALetter('1')));
+        for prio in 1..=52 {
+            let item = Item::from_prio(prio).unwrap();
+            assert_eq!(Item::try_from(item.as_char()), Ok(item));
         }
+        assert_eq!(Item::from_prio(53), None);
+    }
+
+    #[test]
+    fn set_algebra() {
+        let left: ItemSet = "vJrwpWtwJgWr".parse().unwrap();
+        let right: ItemSet = "hcsFMMfFFhFp".parse().unwrap();
+        assert_eq!((left & right).single().unwrap().as_char(), 'p');
+        assert_eq!((left | right).len(), left.len() + right.len() - 1);
+
+        let group = [
+            "vJrwpWtwJgWrhcsFMMfFFhFp",
+            "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+        ]
+        .map(|s| s.parse::<ItemSet>().unwrap());
+        let shared = ItemSet::common(group);
+        assert_eq!(shared.to_string(), "frsFM");
+        assert_eq!(shared.single(), Err(ItemError::MultipleShared(shared)));
+        assert_eq!(ItemSet::common([]).single(), Err(ItemError::NoneShared));
+        assert!("ab1".parse::<ItemSet>().is_err());
     }
 }
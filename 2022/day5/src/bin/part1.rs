@@ -1,7 +1,11 @@
 use std::fs;
 
 use color_eyre::eyre::{eyre, Context, Result};
-use day5::{instructions::parse_instructions, stacks::parse_stacks};
+use day5::{
+    crane::{Crane, CraneModel},
+    instructions::parse_instructions,
+    stacks::parse_stacks,
+};
 use log::debug;
 
 #[tokio::main]
@@ -15,7 +19,7 @@ async fn main() -> Result<()> {
         "Malformed input, initial stacks and instructions must be separated by empty newline"
     ))?;
 
-    let mut stacks = match parse_stacks(stack_input) {
+    let stacks = match parse_stacks(stack_input) {
         Ok((_, stacks)) => stacks,
         Err(e) => return Err(eyre!(e.to_string())),
     };
@@ -24,26 +28,12 @@ async fn main() -> Result<()> {
         Err(e) => return Err(eyre!(e.to_string())),
     };
 
-    for inst in instructions {
-        debug!("Instruction: {}", inst);
-        let mut new_from = stacks.get(inst.from).unwrap().to_owned();
-        let mut new_to = stacks.get(inst.to).unwrap().to_owned();
-        debug!("Current from and to: {}, {}", new_from, new_to);
-        for _ in 0..inst.amount {
-            new_to.0.push(new_from.0.pop().unwrap());
-        }
-        debug!("Updated: {}, {}", new_from, new_to);
-        let _ = std::mem::replace(&mut stacks[inst.from], new_from);
-        _ = std::mem::replace(&mut stacks[inst.to], new_to);
+    for model in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+        let mut crane = Crane::new(model, stacks.clone(), instructions.clone());
+        crane.run()?;
+        debug!("Final stacks with {:?}:\n{}", model, crane.drawing());
+        println!("Final tops with {:?}: {}", model, crane.tops());
     }
 
-    println!(
-        "Final tops: {}",
-        stacks
-            .iter()
-            .map(|s| s.0.last().unwrap())
-            .fold(String::new(), |a, b| a + b.to_string().as_str())
-    );
-
     Ok(())
 }
@@ -0,0 +1,208 @@
+use log::debug;
+use thiserror::Error;
+
+use crate::{
+    instructions::Instruction,
+    stacks::{Stack, StackDrawing},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Error)]
+pub enum CraneError {
+    #[error("Step {step}: there is no stack {stack}")]
+    NoSuchStack { step: usize, stack: usize },
+    #[error(
+        "Step {step}: cannot move {needed} crates from stack {stack}, it only has {available}"
+    )]
+    NotEnoughCrates {
+        step: usize,
+        stack: usize,
+        needed: usize,
+        available: usize,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CraneModel {
+    /// Moves crates one at a time, reversing their order
+    CrateMover9000,
+    /// Moves all crates at once, keeping their order
+    CrateMover9001,
+}
+
+/// Runs a list of instructions on a set of stacks, one step at a time
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Crane {
+    model: CraneModel,
+    stacks: Vec<Stack>,
+    program: Vec<Instruction>,
+    step: usize,
+}
+impl Crane {
+    pub fn new(model: CraneModel, stacks: Vec<Stack>, program: Vec<Instruction>) -> Self {
+        Crane {
+            model,
+            stacks,
+            program,
+            step: 0,
+        }
+    }
+
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    /// Number of instructions applied so far
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.program.len()
+    }
+
+    /// The crates on top of each stack, skipping empty stacks
+    pub fn tops(&self) -> String {
+        self.stacks.iter().filter_map(|s| s.0.last()).collect()
+    }
+
+    /// The stacks in the puzzle's drawing format
+    pub fn drawing(&self) -> String {
+        self.stacks.to_drawing()
+    }
+
+    /// Apply the next instruction and return it, or `None` once the program is done.
+    ///
+    /// Invalid instructions leave the stacks untouched, so a failed step can be inspected.
+    pub fn next_step(&mut self) -> Option<Result<Instruction, CraneError>> {
+        let inst = *self.program.get(self.step)?;
+        Some(self.apply(&inst).map(|_| {
+            self.step += 1;
+            inst
+        }))
+    }
+
+    /// Run all remaining instructions
+    pub fn run(&mut self) -> Result<(), CraneError> {
+        while let Some(result) = self.next_step() {
+            result?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, inst: &Instruction) -> Result<(), CraneError> {
+        debug!("Step {}: {}", self.step, inst);
+        let (from, to) = (self.index(inst.from)?, self.index(inst.to)?);
+        let available = self.stacks[from].0.len();
+        if available < inst.amount {
+            return Err(CraneError::NotEnoughCrates {
+                step: self.step,
+                stack: inst.from,
+                needed: inst.amount,
+                available,
+            });
+        }
+
+        let mut moved = self.stacks[from].0.split_off(available - inst.amount);
+        // Moving crates one at a time onto the same stack puts them back where they were
+        if self.model == CraneModel::CrateMover9000 && from != to {
+            moved.reverse();
+        }
+        self.stacks[to].0.append(&mut moved);
+        Ok(())
+    }
+
+    /// Index into `stacks` for a stack numbered from 1
+    fn index(&self, stack: usize) -> Result<usize, CraneError> {
+        stack
+            .checked_sub(1)
+            .filter(|i| *i < self.stacks.len())
+            .ok_or(CraneError::NoSuchStack {
+                step: self.step,
+                stack,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instructions::parse_instructions, stacks::parse_stacks};
+
+    const EXAMPLE_STACKS: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+    const EXAMPLE_MOVES: &str =
+        "move 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2\n";
+
+    fn crane(model: CraneModel) -> Crane {
+        let (_, stacks) = parse_stacks(EXAMPLE_STACKS).unwrap();
+        let (_, program) = parse_instructions(EXAMPLE_MOVES).unwrap();
+        Crane::new(model, stacks, program)
+    }
+
+    #[test]
+    fn both_models() {
+        let mut old = crane(CraneModel::CrateMover9000);
+        old.run().unwrap();
+        assert_eq!(old.tops(), "CMZ");
+        let mut new = crane(CraneModel::CrateMover9001);
+        new.run().unwrap();
+        assert_eq!(new.tops(), "MCD");
+    }
+
+    #[test]
+    fn step_by_step() {
+        let mut crane = crane(CraneModel::CrateMover9000);
+        assert_eq!(crane.drawing(), EXAMPLE_STACKS);
+        crane.next_step().unwrap().unwrap();
+        assert_eq!(
+            crane.drawing(),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+        );
+        crane.run().unwrap();
+        assert!(crane.is_finished());
+        assert!(crane.next_step().is_none());
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let (_, stacks) = parse_stacks(EXAMPLE_STACKS).unwrap();
+        let (_, program) = parse_instructions("move 4 from 1 to 2\nmove 1 from 4 to 1\n").unwrap();
+        let mut crane = Crane::new(CraneModel::CrateMover9001, stacks.clone(), program.clone());
+        assert_eq!(
+            crane.next_step(),
+            Some(Err(CraneError::NotEnoughCrates {
+                step: 0,
+                stack: 1,
+                needed: 4,
+                available: 2
+            }))
+        );
+        assert_eq!(crane.stacks(), stacks);
+        let mut crane = Crane::new(
+            CraneModel::CrateMover9001,
+            stacks.clone(),
+            program[1..].to_vec(),
+        );
+        assert_eq!(
+            crane.run(),
+            Err(CraneError::NoSuchStack { step: 0, stack: 4 })
+        );
+
+        let (_, program) = parse_instructions("move 1 from 0 to 1\n").unwrap();
+        let mut crane = Crane::new(CraneModel::CrateMover9000, stacks, program);
+        assert_eq!(
+            crane.run(),
+            Err(CraneError::NoSuchStack { step: 0, stack: 0 })
+        );
+    }
+
+    #[test]
+    fn move_onto_same_stack() {
+        let (_, program) = parse_instructions("move 2 from 2 to 2\n").unwrap();
+        for model in [CraneModel::CrateMover9000, CraneModel::CrateMover9001] {
+            let (_, stacks) = parse_stacks(EXAMPLE_STACKS).unwrap();
+            let mut crane = Crane::new(model, stacks.clone(), program.clone());
+            crane.run().unwrap();
+            assert_eq!(crane.stacks(), stacks);
+        }
+    }
+}
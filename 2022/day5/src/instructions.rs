@@ -10,15 +10,17 @@ use nom::{
     Finish, IResult,
 };
 
-#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Instruction {
+    /// Stack number as written in the input, starting at 1
     pub from: usize,
+    /// Stack number as written in the input, starting at 1
     pub to: usize,
     pub amount: usize,
 }
 impl Display for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} -> {} -> {}", self.from + 1, self.amount, self.to + 1)
+        write!(f, "{} -> {} -> {}", self.from, self.amount, self.to)
     }
 }
 
@@ -32,9 +34,8 @@ fn instruction_lines(input: &str) -> IResult<&str, Vec<Instruction>> {
 
 fn instruction_line(input: &str) -> IResult<&str, Instruction> {
     map(pair(amount, from_to), |(amount, (from, to))| Instruction {
-        // Input indexes by 1, our Stack vec starts at 0
-        from: (from - 1) as usize,
-        to: (to - 1) as usize,
+        from: from as usize,
+        to: to as usize,
         amount: amount as usize,
     })(input)
 }
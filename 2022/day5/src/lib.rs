@@ -1,2 +1,3 @@
+pub mod crane;
 pub mod instructions;
 pub mod stacks;
@@ -4,52 +4,113 @@ use itertools::Itertools;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{anychar, char, line_ending},
-    combinator::map,
-    error::Error,
-    multi::{many1, many_till},
-    sequence::{delimited, terminated},
+    character::complete::{anychar, char, line_ending, space0},
+    combinator::{map, opt},
+    error::{Error, ErrorKind},
+    multi::{many0, many1, many_till},
+    sequence::{delimited, pair, preceded, terminated},
     Finish, IResult,
 };
 
 pub type Crate = char;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub struct Stack(pub Vec<Crate>);
 impl Display for Stack {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.iter().fold(Ok(()), |result, new| {
-            result.and_then(|_| write!(f, "[{}]", new))
-        })
+        self.0.iter().try_for_each(|c| write!(f, "[{}]", c))
     }
 }
 
+/// Displays stacks in the puzzle's drawing format, see [`StackDrawing`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Drawing<'a>(pub &'a [Stack]);
+impl Display for Drawing<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.0.iter().map(|s| s.0.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let line = self
+                .0
+                .iter()
+                .map(|s| match s.0.get(level) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_string(),
+                })
+                .join(" ");
+            writeln!(f, "{}", line)?;
+        }
+        write!(
+            f,
+            "{}",
+            (1..=self.0.len()).map(|i| format!("{:^3}", i)).join(" ")
+        )
+    }
+}
+
+/// Render stacks exactly like the puzzle input: the tallest stack on the first line, every line
+/// padded to the full width, and the stack numbers on the last line without a trailing newline.
+///
+/// This is the inverse of [`parse_stacks`].
+pub trait StackDrawing {
+    fn drawing(&self) -> Drawing<'_>;
+    fn to_drawing(&self) -> String {
+        self.drawing().to_string()
+    }
+}
+impl StackDrawing for [Stack] {
+    fn drawing(&self) -> Drawing<'_> {
+        Drawing(self)
+    }
+}
+
+/// Parse a stack drawing, including the line with the stack numbers.
+///
+/// The numbers have to count up from 1, and determine how many stacks there are. Crate lines
+/// may be shorter than the number line, the missing cells are empty.
 pub fn parse_stacks(input: &str) -> Result<(&str, Vec<Stack>), Error<&str>> {
-    let (rest, mut lines) = crate_lines(input).finish()?;
-
-    lines.reverse();
-
-    let stacks = lines
-        .get(0)
-        .unwrap()
-        .iter()
-        .enumerate()
-        .map(|(idx, _)| {
-            Stack(
-                lines
-                    .iter()
-                    .map(|l| l.get(idx).unwrap())
-                    .map_while(|c| c.to_owned())
-                    .collect_vec(),
-            )
-        })
-        .collect_vec();
+    let (rest, (lines, labels)) = pair(crate_lines, label_line)(input).finish()?;
+    let invalid = || Error::new(input, ErrorKind::Verify);
+
+    if labels.iter().zip(1..).any(|(label, i)| *label != i) {
+        return Err(invalid());
+    }
+    if lines.iter().any(|l| l.len() > labels.len()) {
+        return Err(invalid());
+    }
+
+    let mut stacks = vec![Stack::default(); labels.len()];
+    for line in lines.iter().rev() {
+        for (stack, c) in stacks.iter_mut().zip(line) {
+            if let Some(c) = c {
+                stack.0.push(*c);
+            }
+        }
+    }
+    // Crates can't float, so every stack must be as tall as its topmost crate
+    for (idx, stack) in stacks.iter().enumerate() {
+        let top = lines.len()
+            - lines
+                .iter()
+                .position(|l| matches!(l.get(idx), Some(Some(_))))
+                .unwrap_or(lines.len());
+        if top != stack.0.len() {
+            return Err(invalid());
+        }
+    }
 
     Ok((rest, stacks))
 }
 
+/// The stack numbers below the drawing, such as ` 1   2   3 `
+fn label_line(input: &str) -> IResult<&str, Vec<u32>> {
+    terminated(
+        many1(preceded(space0, nom::character::complete::u32)),
+        pair(space0, opt(line_ending)),
+    )(input)
+}
+
 fn crate_lines(input: &str) -> IResult<&str, Vec<Vec<Option<Crate>>>> {
-    many1(crate_line)(input)
+    many0(crate_line)(input)
 }
 
 fn crate_line(input: &str) -> IResult<&str, Vec<Option<Crate>>> {
@@ -80,3 +141,46 @@ fn crate_box(input: &str) -> IResult<&str, char> {
 fn empty(input: &str) -> IResult<&str, char> {
     tag("   ")(input).map(|(rest, _)| (rest, ' '))
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn stacks() -> impl Strategy<Value = Vec<Stack>> {
+        prop::collection::vec(
+            prop::collection::vec(prop::char::range('A', 'Z'), 0..8).prop_map(Stack),
+            1..12,
+        )
+    }
+
+    #[test]
+    fn example_drawing() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let (rest, stacks) = parse_stacks(drawing).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(stacks[0], Stack(vec!['Z', 'N']));
+        assert_eq!(stacks.to_drawing(), drawing);
+    }
+
+    #[test]
+    fn invalid_drawings() {
+        // Floating crate
+        assert!(parse_stacks("[A]    \n    [B]\n 1   2 ").is_err());
+        // Wrong numbering
+        assert!(parse_stacks("[A] [B]\n 1   3 ").is_err());
+        // More crates than stacks
+        assert!(parse_stacks("[A] [B]\n 1 ").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip(stacks in stacks()) {
+            let drawing = stacks.to_drawing();
+            let (rest, parsed) = parse_stacks(&drawing).unwrap();
+            prop_assert_eq!(rest, "");
+            prop_assert_eq!(parsed, stacks);
+        }
+    }
+}
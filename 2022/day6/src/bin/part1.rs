@@ -1,28 +1,17 @@
-use std::{collections::HashSet, fs::read_to_string};
-
-const WINDOW_SIZE: usize = 14;
+use std::fs::File;
 
 use color_eyre::eyre::{eyre, Context, Result};
-#[tokio::main]
-async fn main() -> Result<()> {
+use day6::marker::{first_marker, PACKET_MARKER};
+
+fn main() -> Result<()> {
     color_eyre::install().wrap_err("Setting up color-eyre")?;
     env_logger::init();
 
-    let input = read_to_string("input.txt")?;
+    let input = File::open("input.txt").wrap_err("Opening input.txt")?;
 
-    let offset = input
-        .chars()
-        .collect::<Vec<char>>()
-        .windows(WINDOW_SIZE)
-        .enumerate()
-        .find_map(|(offset, window)| {
-            if window.iter().collect::<HashSet<&char>>().len() == WINDOW_SIZE {
-                Some(offset + WINDOW_SIZE)
-            } else {
-                None
-            }
-        })
-        .ok_or(eyre!("No marker found"))?;
+    let offset = first_marker(input, PACKET_MARKER)
+        .wrap_err("Reading input.txt")?
+        .ok_or(eyre!("No start-of-packet marker found"))?;
 
     println!("Offset: {}", offset);
 
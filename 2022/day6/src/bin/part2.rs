@@ -0,0 +1,19 @@
+use std::fs::File;
+
+use color_eyre::eyre::{eyre, Context, Result};
+use day6::marker::{first_marker, MESSAGE_MARKER};
+
+fn main() -> Result<()> {
+    color_eyre::install().wrap_err("Setting up color-eyre")?;
+    env_logger::init();
+
+    let input = File::open("input.txt").wrap_err("Opening input.txt")?;
+
+    let offset = first_marker(input, MESSAGE_MARKER)
+        .wrap_err("Reading input.txt")?
+        .ok_or(eyre!("No start-of-message marker found"))?;
+
+    println!("Offset: {}", offset);
+
+    Ok(())
+}
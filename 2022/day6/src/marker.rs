@@ -0,0 +1,135 @@
+use std::io::{self, BufReader, Bytes, Read};
+
+/// Start-of-packet markers are 4 distinct bytes
+pub const PACKET_MARKER: usize = 4;
+/// Start-of-message markers are 14 distinct bytes
+pub const MESSAGE_MARKER: usize = 14;
+
+/// Tracks the last `size` bytes of a stream and whether they are all different.
+///
+/// Keeps a count per byte value and the number of values that occur more than once in the
+/// window, so each new byte is handled in constant time regardless of the window size.
+#[derive(Debug, Clone)]
+pub struct MarkerDetector {
+    window: Vec<u8>,
+    counts: [usize; 256],
+    repeated: usize,
+    position: usize,
+}
+impl MarkerDetector {
+    /// Create a detector for markers of `size` bytes. Will `panic!()` if size is 0
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "Markers need at least one byte");
+        MarkerDetector {
+            window: vec![0; size],
+            counts: [0; 256],
+            repeated: 0,
+            position: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Number of bytes seen so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Add the next byte. Returns whether the last `size` bytes are now all distinct
+    pub fn push(&mut self, byte: u8) -> bool {
+        let slot = self.position % self.window.len();
+        if self.position >= self.window.len() {
+            let old = self.window[slot] as usize;
+            self.counts[old] -= 1;
+            if self.counts[old] == 1 {
+                self.repeated -= 1;
+            }
+        }
+        self.window[slot] = byte;
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.repeated += 1;
+        }
+        self.position += 1;
+        self.is_marker()
+    }
+
+    /// Whether the window is full and contains no byte twice
+    pub fn is_marker(&self) -> bool {
+        self.position >= self.window.len() && self.repeated == 0
+    }
+}
+
+/// Positions right after every marker in a stream, see [`markers`]
+pub struct Markers<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    detector: MarkerDetector,
+}
+impl<R: Read> Iterator for Markers<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in self.bytes.by_ref() {
+            match byte {
+                Ok(b) if self.detector.push(b) => return Some(Ok(self.detector.position())),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+/// Find all markers of `size` distinct bytes in `reader`, reading it byte by byte.
+///
+/// Yields the number of bytes read up to and including each marker, which is the puzzle answer
+/// for the first one. Markers may overlap
+pub fn markers<R: Read>(reader: R, size: usize) -> Markers<R> {
+    Markers {
+        bytes: BufReader::new(reader).bytes(),
+        detector: MarkerDetector::new(size),
+    }
+}
+
+/// The end of the first marker of `size` distinct bytes, if there is one
+pub fn first_marker<R: Read>(reader: R, size: usize) -> io::Result<Option<usize>> {
+    markers(reader, size).next().transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let examples = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (input, packet, message) in examples {
+            assert_eq!(
+                first_marker(input.as_bytes(), PACKET_MARKER).unwrap(),
+                Some(packet)
+            );
+            assert_eq!(
+                first_marker(input.as_bytes(), MESSAGE_MARKER).unwrap(),
+                Some(message)
+            );
+        }
+    }
+
+    #[test]
+    fn all_markers() {
+        let found: Vec<usize> = markers("aabcab".as_bytes(), 3)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(found, vec![4, 5, 6]);
+        assert_eq!(first_marker("abab".as_bytes(), 3).unwrap(), None);
+        assert_eq!(first_marker("a".as_bytes(), 1).unwrap(), Some(1));
+    }
+}
@@ -1,8 +1,12 @@
-use std::{collections::HashMap, fs::read_to_string};
+use std::fs::read_to_string;
 
 use color_eyre::{eyre::eyre, Result};
-use day7::{filetree::DirTree, parser::parse_terminal};
-use itertools::Itertools;
+use day7::{
+    filetree::{at_least, at_most, DirTree},
+    parser::parse_terminal,
+    report::{du_report, tree_report},
+};
+use log::debug;
 
 const LIMIT: u64 = 100000;
 const NEEDED_FREE: u64 = 30000000;
@@ -19,40 +23,19 @@ fn main() -> Result<()> {
         Err(e) => return Err(eyre!("Parse Error: {}", e)),
     };
 
-    let tree = DirTree::build(&commands);
-    let under_limit_total: u64 = tree
-        .get()
-        .traverse_pre_order(tree.get().root_node_id().unwrap())
-        .unwrap()
-        .filter_map(|n| {
-            let size = tree.get_dir_size(n);
-            if size <= LIMIT {
-                Some(size)
-            } else {
-                None
-            }
-        })
-        .sum();
+    let tree = DirTree::build(&commands)?;
+    debug!("Filesystem:\n{}", tree_report(&tree));
+    debug!("Directory sizes:\n{}", du_report(&tree));
+
+    let under_limit_total: u64 = tree.dirs_where(at_most(LIMIT)).map(|(_, s)| s).sum();
     println!("Total under limit: {}", under_limit_total);
 
-    let total_used: u64 =
-        tree.get_dir_size(tree.get().get(tree.get().root_node_id().unwrap()).unwrap());
-    let delete_at_least = NEEDED_FREE - (TOTAL_FS - total_used);
-    let delete_dir_with_size: u64 = tree
-        .get()
-        .traverse_pre_order(tree.get().root_node_id().unwrap())
-        .unwrap()
-        .filter_map(|n| {
-            let size = tree.get_dir_size(n);
-            if size >= delete_at_least {
-                Some(size)
-            } else {
-                None
-            }
-        })
-        .sorted()
-        .next()
-        .unwrap();
+    let delete_at_least = NEEDED_FREE - (TOTAL_FS - tree.size(tree.root()));
+    let delete_dir_with_size = tree
+        .dirs_where(at_least(delete_at_least))
+        .map(|(_, s)| s)
+        .min()
+        .ok_or_else(|| eyre!("No directory is large enough"))?;
     println!("Delete with size: {}", delete_dir_with_size);
 
     Ok(())
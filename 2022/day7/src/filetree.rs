@@ -1,90 +1,337 @@
-use std::{clone, collections::HashMap, fmt::Display, hash::Hash};
+use std::collections::HashMap;
 
-use id_tree::{Node, NodeId, Tree, TreeBuilder};
-use itertools::Itertools;
+use id_tree::{InsertBehavior, Node, NodeId, Tree};
 use log::debug;
+use thiserror::Error;
 
 use crate::parser::{Command, Listing, Move};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+pub enum FsError {
+    #[error("Command {index}: cannot leave the root directory")]
+    AboveRoot { index: usize },
+    #[error("Command {index}: `{path}` is a file, not a directory")]
+    NotADirectory { index: usize, path: String },
+    #[error("Command {index}: `{path}` is a directory, but was listed as a file")]
+    NotAFile { index: usize, path: String },
+    #[error(
+        "Command {index}: `{path}` was listed with size {size}, but had size {previous} before"
+    )]
+    SizeChanged {
+        index: usize,
+        path: String,
+        size: u64,
+        previous: u64,
+    },
+    #[error("Command {index}: invalid name `{name}`")]
+    InvalidName { index: usize, name: String },
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Dir {
     pub name: String,
     pub files: HashMap<String, u64>,
 }
 
+/// A virtual filesystem rebuilt from a terminal transcript.
+///
+/// The root directory is always called `/`. Directories are created when they are first seen,
+/// either in an `ls` listing or by `cd`-ing into them.
 #[derive(Clone, Debug)]
 pub struct DirTree {
     tree: Tree<Dir>,
+    root: NodeId,
+    /// Total size of every directory, including its subdirectories
+    sizes: HashMap<NodeId, u64>,
 }
 impl DirTree {
+    /// An empty filesystem with just the root directory
+    pub fn new() -> Self {
+        let mut tree = Tree::new();
+        let root = tree
+            .insert(
+                Node::new(Dir {
+                    name: "/".to_string(),
+                    ..Default::default()
+                }),
+                InsertBehavior::AsRoot,
+            )
+            .expect("Inserting the root into an empty tree");
+        let sizes = HashMap::from([(root.clone(), 0)]);
+        DirTree { tree, root, sizes }
+    }
+
     pub fn get(&self) -> &Tree<Dir> {
         &self.tree
     }
 
-    pub fn build(cmds: &[Command]) -> Self {
-        use id_tree::InsertBehavior::*;
+    pub fn root(&self) -> &NodeId {
+        &self.root
+    }
 
-        let mut tree = Tree::new();
+    /// Replay a terminal session, starting in the root directory
+    pub fn build(cmds: &[Command]) -> Result<Self, FsError> {
+        let mut fs = Self::new();
+        let mut current = fs.root.clone();
+        for (index, cmd) in cmds.iter().enumerate() {
+            debug!("{}: {}", fs.path(&current), cmd);
+            match cmd {
+                Command::ChangeDir(Move::Out) => current = fs.change_dir(&current, "..", index)?,
+                Command::ChangeDir(Move::To(path)) => {
+                    current = fs.change_dir(&current, path, index)?
+                }
+                Command::List(listing) => fs.add_listing(&current, listing, index)?,
+            }
+        }
+        fs.compute_sizes();
+        Ok(fs)
+    }
 
-        let mut current = match cmds.get(0).unwrap() {
-            Command::ChangeDir(Move::To(root)) => tree
-                .insert(
-                    Node::new(Dir {
-                        name: root.to_string(),
-                        ..Default::default()
-                    }),
-                    AsRoot,
-                )
-                .unwrap(),
-            _ => panic!(),
+    /// Find the directory at `path`, relative to `from` unless it starts with `/`
+    pub fn resolve(&self, from: &NodeId, path: &str) -> Option<NodeId> {
+        let mut current = if path.starts_with('/') {
+            self.root.clone()
+        } else {
+            from.clone()
         };
+        for part in path.split('/') {
+            current = match part {
+                "" | "." => current,
+                ".." => self.node(&current).parent()?.clone(),
+                name => self.child(&current, name)?,
+            };
+        }
+        Some(current)
+    }
 
-        for cmd in cmds {
-            match cmd {
-                Command::ChangeDir(mv) => match mv {
-                    Move::To(dest) => {
-                        current = tree
-                            .insert(
-                                Node::new(Dir {
-                                    name: dest.to_string(),
-                                    ..Default::default()
-                                }),
-                                UnderNode(&current),
-                            )
-                            .unwrap()
+    /// The absolute path of a directory
+    pub fn path(&self, dir: &NodeId) -> String {
+        let mut names: Vec<&str> = self
+            .tree
+            .ancestor_ids(dir)
+            .expect("Directory is part of the tree")
+            .map(|id| self.node(id).data().name.as_str())
+            .collect();
+        names.reverse();
+        names.push(&self.node(dir).data().name);
+        // Skip the root, its name is the leading separator
+        format!("/{}", names[1..].join("/"))
+    }
+
+    /// Total size of a directory, including its subdirectories
+    pub fn size(&self, dir: &NodeId) -> u64 {
+        self.sizes[dir]
+    }
+
+    /// All directories with a total size matching `predicate`, see [`at_most`] and [`at_least`]
+    pub fn dirs_where(
+        &self,
+        predicate: impl Fn(u64) -> bool,
+    ) -> impl Iterator<Item = (&NodeId, u64)> {
+        self.sizes
+            .iter()
+            .map(|(id, size)| (id, *size))
+            .filter(move |(_, size)| predicate(*size))
+    }
+
+    /// Sum up the directory sizes bottom-up, so that each directory is only visited once
+    fn compute_sizes(&mut self) {
+        let mut sizes = HashMap::new();
+        for id in self
+            .tree
+            .traverse_post_order_ids(&self.root)
+            .expect("Root is part of the tree")
+        {
+            let node = self.node(&id);
+            let own_files: u64 = node.data().files.values().sum();
+            let subdirs: u64 = node.children().iter().map(|c| sizes[c]).sum();
+            sizes.insert(id, own_files + subdirs);
+        }
+        self.sizes = sizes;
+    }
+
+    fn node(&self, id: &NodeId) -> &Node<Dir> {
+        self.tree.get(id).expect("Node IDs are never removed")
+    }
+
+    fn child(&self, dir: &NodeId, name: &str) -> Option<NodeId> {
+        self.node(dir)
+            .children()
+            .iter()
+            .find(|c| self.node(c).data().name == name)
+            .cloned()
+    }
+
+    /// Follow `path` from `from`, creating missing directories along the way
+    fn change_dir(&mut self, from: &NodeId, path: &str, index: usize) -> Result<NodeId, FsError> {
+        let mut current = if path.starts_with('/') {
+            self.root.clone()
+        } else {
+            from.clone()
+        };
+        for part in path.split('/') {
+            current = match part {
+                "" | "." => current,
+                ".." => self
+                    .node(&current)
+                    .parent()
+                    .cloned()
+                    .ok_or(FsError::AboveRoot { index })?,
+                name => self.make_dir(&current, name, index)?,
+            };
+        }
+        Ok(current)
+    }
+
+    /// Return the subdirectory `name` of `parent`, creating it if needed
+    fn make_dir(&mut self, parent: &NodeId, name: &str, index: usize) -> Result<NodeId, FsError> {
+        if let Some(existing) = self.child(parent, name) {
+            return Ok(existing);
+        }
+        if self.node(parent).data().files.contains_key(name) {
+            return Err(FsError::NotADirectory {
+                index,
+                path: self.join(parent, name),
+            });
+        }
+        Ok(self
+            .tree
+            .insert(
+                Node::new(Dir {
+                    name: name.to_string(),
+                    ..Default::default()
+                }),
+                InsertBehavior::UnderNode(parent),
+            )
+            .expect("Parent is part of the tree"))
+    }
+
+    /// Merge an `ls` listing into `dir`. Entries seen before must match the previous listing
+    fn add_listing(
+        &mut self,
+        dir: &NodeId,
+        listing: &[Listing],
+        index: usize,
+    ) -> Result<(), FsError> {
+        for entry in listing {
+            let name = match entry {
+                Listing::File(f) => &f.name,
+                Listing::Dir(d) => d,
+            };
+            if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+                return Err(FsError::InvalidName {
+                    index,
+                    name: name.to_string(),
+                });
+            }
+            match entry {
+                Listing::Dir(name) => {
+                    self.make_dir(dir, name, index)?;
+                }
+                Listing::File(f) => {
+                    if self.child(dir, &f.name).is_some() {
+                        return Err(FsError::NotAFile {
+                            index,
+                            path: self.join(dir, &f.name),
+                        });
+                    }
+                    let path = self.join(dir, &f.name);
+                    let files = &mut self
+                        .tree
+                        .get_mut(dir)
+                        .expect("Directory is part of the tree")
+                        .data_mut()
+                        .files;
+                    match files.insert(f.name.clone(), f.size) {
+                        Some(previous) if previous != f.size => {
+                            return Err(FsError::SizeChanged {
+                                index,
+                                path,
+                                size: f.size,
+                                previous,
+                            })
+                        }
+                        _ => (),
                     }
-                    Move::Out => current = tree.get(&current).unwrap().parent().unwrap().clone(),
-                },
-                Command::List(listing) => {
-                    tree.get_mut(&current).unwrap().data_mut().files = listing
-                        .iter()
-                        .filter_map(|l| match l {
-                            Listing::File(f) => Some((f.name.clone(), f.size)),
-                            Listing::Dir(_) => None,
-                        })
-                        .collect();
                 }
             }
         }
-        DirTree { tree }
+        Ok(())
     }
 
-    pub fn get_dir_size(&self, dir: &Node<Dir>) -> u64 {
-        let own_files: u64 = dir.data().files.values().sum();
-        let subdirs: u64 = dir
-            .children()
-            .iter()
-            .map(|c| self.get_dir_size(self.tree.get(c).unwrap()))
-            .sum();
-        own_files + subdirs
+    fn join(&self, dir: &NodeId, name: &str) -> String {
+        let path = self.path(dir);
+        match path.as_str() {
+            "/" => format!("/{}", name),
+            _ => format!("{}/{}", path, name),
+        }
     }
 }
+/// Matches directories of at most `limit` in size
+pub fn at_most(limit: u64) -> impl Fn(u64) -> bool {
+    move |size| size <= limit
+}
+
+/// Matches directories of at least `limit` in size
+pub fn at_least(limit: u64) -> impl Fn(u64) -> bool {
+    move |size| size >= limit
+}
 
-impl Default for Dir {
+impl Default for DirTree {
     fn default() -> Self {
-        Self {
-            name: "".to_string(),
-            files: HashMap::new(),
-        }
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_terminal;
+
+    fn build(transcript: &str) -> Result<DirTree, FsError> {
+        DirTree::build(&parse_terminal(transcript).unwrap())
+    }
+
+    #[test]
+    fn revisits_and_paths() {
+        let fs = build(
+            "$ ls\ndir a\n10 x\n$ cd a/b\n$ ls\n20 y\n$ cd /\n$ cd a\n$ ls\ndir b\n5 z\n$ cd ../a/b\n$ ls\n20 y\n",
+        )
+        .unwrap();
+        let root = fs.root();
+        let b = fs.resolve(root, "/a/b").unwrap();
+        assert_eq!(fs.path(&b), "/a/b");
+        assert_eq!(fs.resolve(&b, "../.."), Some(root.clone()));
+        assert_eq!(fs.resolve(root, "c"), None);
+        // `a` was entered three times and listed once, but still has a single `b`
+        assert_eq!(fs.get().children(root).unwrap().count(), 1);
+        assert_eq!(fs.size(root), 35);
+        assert_eq!(fs.size(&b), 20);
+    }
+
+    #[test]
+    fn inconsistent_transcripts() {
+        assert_eq!(
+            build("$ cd ..\n").unwrap_err(),
+            FsError::AboveRoot { index: 0 }
+        );
+        assert_eq!(
+            build("$ ls\n10 a\n$ cd a\n").unwrap_err(),
+            FsError::NotADirectory {
+                index: 1,
+                path: "/a".to_string()
+            }
+        );
+        assert_eq!(
+            build("$ ls\ndir a\n10 a\n").unwrap_err(),
+            FsError::NotAFile {
+                index: 0,
+                path: "/a".to_string()
+            }
+        );
+        assert!(matches!(
+            build("$ ls\n10 a\n$ ls\n11 a\n").unwrap_err(),
+            FsError::SizeChanged { index: 1, .. }
+        ));
     }
 }
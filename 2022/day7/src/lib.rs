@@ -1,2 +1,3 @@
 pub mod filetree;
 pub mod parser;
+pub mod report;
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use id_tree::NodeId;
+use itertools::Itertools;
+
+use crate::filetree::DirTree;
+
+/// An entry of a directory, for sorting files and directories together
+enum Entry<'a> {
+    Dir(&'a NodeId),
+    File(u64),
+}
+
+/// List the whole filesystem like `tree`, with entries sorted by name
+pub fn tree_report(fs: &DirTree) -> String {
+    let mut out = format!("/ (dir, size={})\n", fs.size(fs.root()));
+    write_children(fs, fs.root(), "", &mut out);
+    out
+}
+
+fn write_children(fs: &DirTree, dir: &NodeId, prefix: &str, out: &mut String) {
+    let node = fs.get().get(dir).expect("Directory is part of the tree");
+    let dirs = node.children().iter().map(|c| {
+        let name = fs
+            .get()
+            .get(c)
+            .expect("Child is part of the tree")
+            .data()
+            .name
+            .as_str();
+        (name, Entry::Dir(c))
+    });
+    let files = node
+        .data()
+        .files
+        .iter()
+        .map(|(name, size)| (name.as_str(), Entry::File(*size)));
+    let entries = dirs
+        .chain(files)
+        .sorted_by_key(|(name, _)| *name)
+        .collect_vec();
+
+    for (i, (name, entry)) in entries.iter().enumerate() {
+        let last = i == entries.len() - 1;
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        match entry {
+            Entry::Dir(id) => {
+                let _ = writeln!(
+                    out,
+                    "{}{}{} (dir, size={})",
+                    prefix,
+                    branch,
+                    name,
+                    fs.size(id)
+                );
+                write_children(fs, id, &format!("{}{}", prefix, indent), out);
+            }
+            Entry::File(size) => {
+                let _ = writeln!(out, "{}{}{} (file, size={})", prefix, branch, name, size);
+            }
+        }
+    }
+}
+
+/// List all directories with their total size like `du -h`, largest first
+pub fn du_report(fs: &DirTree) -> String {
+    fs.dirs_where(|_| true)
+        .map(|(id, size)| (size, fs.path(id)))
+        .sorted_by(|(a_size, a_path), (b_size, b_path)| {
+            b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+        })
+        .map(|(size, path)| format!("{}\t{}\n", human_size(size), path))
+        .collect()
+}
+
+/// Format a size with a binary unit suffix, like `du -h`
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filetree::{at_least, at_most},
+        parser::parse_terminal,
+    };
+
+    const EXAMPLE: &str = "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
+
+    #[test]
+    fn example_queries() {
+        let fs = DirTree::build(&parse_terminal(EXAMPLE).unwrap()).unwrap();
+        let small: u64 = fs.dirs_where(at_most(100000)).map(|(_, s)| s).sum();
+        assert_eq!(small, 95437);
+        let needed = 30000000 - (70000000 - fs.size(fs.root()));
+        let smallest = fs.dirs_where(at_least(needed)).map(|(_, s)| s).min();
+        assert_eq!(smallest, Some(24933642));
+    }
+
+    #[test]
+    fn reports() {
+        let fs = DirTree::build(&parse_terminal(EXAMPLE).unwrap()).unwrap();
+        assert_eq!(
+            tree_report(&fs),
+            "\
+/ (dir, size=48381165)
+├── a (dir, size=94853)
+│   ├── e (dir, size=584)
+│   │   └── i (file, size=584)
+│   ├── f (file, size=29116)
+│   ├── g (file, size=2557)
+│   └── h.lst (file, size=62596)
+├── b.txt (file, size=14848514)
+├── c.dat (file, size=8504156)
+└── d (dir, size=24933642)
+    ├── d.ext (file, size=5626152)
+    ├── d.log (file, size=8033020)
+    ├── j (file, size=4060174)
+    └── k (file, size=7214296)
+"
+        );
+        assert_eq!(du_report(&fs), "46M\t/\n24M\t/d\n93K\t/a\n584\t/a/e\n");
+    }
+}
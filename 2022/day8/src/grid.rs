@@ -1,244 +1,176 @@
 use std::fmt::Display;
 
-use itertools::Itertools;
-use log::debug;
+use thiserror::Error;
+
+use crate::view::{ViewRules, Views};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum GridError {
+    #[error("Row {row} has {len} trees, expected {expected}")]
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+}
 
-#[derive(Debug, Clone)]
-pub struct TreeGrid {
-    grid: Vec<Vec<Tree>>,
+/// A rectangular grid of values, stored row by row
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
     rows: usize,
     cols: usize,
 }
 
-impl TreeGrid {
-    pub fn new(matrix: Vec<Vec<u8>>) -> Self {
-        let mut tree_grid = TreeGrid {
-            grid: Vec::new(),
-            rows: 0,
-            cols: 0,
-        };
-        let grid = matrix
-            .iter()
-            .enumerate()
-            .map(|(r, row)| {
-                row.iter()
-                    .enumerate()
-                    .map(|(c, height)| Tree {
-                        row: r,
-                        col: c,
-                        height: *height,
-                    })
-                    .collect()
-            })
-            .collect_vec();
-        tree_grid.rows = grid.len();
-        tree_grid.cols = grid.iter().map(|r: &Vec<Tree>| r.len()).max().unwrap();
-        tree_grid.grid = grid;
-        tree_grid
-    }
-    pub fn at(&self, row: usize, col: usize) -> Option<&Tree> {
-        self.grid.get(row)?.get(col)
+impl<T> Grid<T> {
+    pub fn filled(rows: usize, cols: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Grid {
+            cells: vec![value; rows * cols],
+            rows,
+            cols,
+        }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        self.index_of(row, col).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        self.index_of(row, col).map(|i| &mut self.cells[i])
     }
+
     pub fn height(&self) -> usize {
         self.rows
     }
+
     pub fn width(&self) -> usize {
         self.cols
     }
-}
-impl Display for TreeGrid {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for row in &self.grid {
-            for t in row {
-                write!(f, "{}", t.height)?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+
+    /// All values, row by row
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
     }
-}
 
-#[derive(Debug, Copy, Clone)]
-struct Visibility {
-    range: usize,
-    to_edge: bool,
-}
-impl std::ops::Mul<Visibility> for Visibility {
-    type Output = usize;
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // chunks() panics on 0, an empty grid has no rows anyway
+        self.cells.chunks(self.cols.max(1))
+    }
 
-    fn mul(self, rhs: Visibility) -> Self::Output {
-        self.range * rhs.range
+    /// Apply `f` to every value, keeping the shape
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid {
+            cells: self.cells.iter().map(f).collect(),
+            rows: self.rows,
+            cols: self.cols,
+        }
+    }
+
+    /// Combine two grids of the same shape value by value
+    pub fn zip_with<U, V>(&self, other: &Grid<U>, f: impl Fn(&T, &U) -> V) -> Grid<V> {
+        assert_eq!((self.rows, self.cols), (other.rows, other.cols));
+        Grid {
+            cells: self
+                .cells
+                .iter()
+                .zip(&other.cells)
+                .map(|(a, b)| f(a, b))
+                .collect(),
+            rows: self.rows,
+            cols: self.cols,
+        }
     }
-}
-impl std::ops::Mul<usize> for Visibility {
-    type Output = usize;
 
-    fn mul(self, rhs: usize) -> Self::Output {
-        self.range * rhs
+    /// Draw the grid with one character per value
+    pub fn render(&self, f: impl Fn(&T) -> char) -> String {
+        let mut out = String::with_capacity(self.rows * (self.cols + 1));
+        for row in self.rows() {
+            out.extend(row.iter().map(&f));
+            out.push('\n');
+        }
+        out
     }
-}
-impl std::ops::Mul<Visibility> for usize {
-    type Output = usize;
 
-    fn mul(self, rhs: Visibility) -> Self::Output {
-        self * rhs.range
+    fn index_of(&self, row: usize, col: usize) -> Option<usize> {
+        (row < self.rows && col < self.cols).then_some(row * self.cols + col)
     }
 }
 
+/// Tree heights, with the first row at the top
 #[derive(Debug, Clone)]
-pub struct Tree {
-    row: usize,
-    col: usize,
-    pub height: u8,
+pub struct TreeGrid {
+    heights: Grid<u8>,
 }
-impl Tree {
-    pub fn is_visible(&self, grid: &TreeGrid) -> bool {
-        if self.col == 0
-            || self.row == 0
-            || grid.at(self.row, self.col + 1).is_none()
-            || grid.at(self.row + 1, self.col).is_none()
-        {
-            debug!("Tree {} is visible because it's on the outside", self);
-            return true;
-        }
-        if self.visibility_to_left(grid).to_edge {
-            debug!("Tree {} is visible from the left", self);
-            return true;
-        }
-        if self.visibility_to_right(grid).to_edge {
-            debug!("Tree {} is visible from the right", self);
-            return true;
-        }
-        if self.visibility_to_top(grid).to_edge {
-            debug!("Tree {} is visible from the top", self);
-            return true;
-        }
-        if self.visibility_to_bot(grid).to_edge {
-            debug!("Tree {} is visible from the bottom", self);
-            return true;
-        }
 
-        debug!("Tree {} is not visible from any side", self);
-        false
+impl TreeGrid {
+    /// Create a grid from rows of tree heights. All rows need to have the same length
+    pub fn new(matrix: Vec<Vec<u8>>) -> Result<Self, GridError> {
+        let cols = matrix.first().map_or(0, |r| r.len());
+        if let Some((row, r)) = matrix.iter().enumerate().find(|(_, r)| r.len() != cols) {
+            return Err(GridError::RaggedRow {
+                row,
+                len: r.len(),
+                expected: cols,
+            });
+        }
+        Ok(TreeGrid {
+            heights: Grid {
+                rows: matrix.len(),
+                cols,
+                cells: matrix.into_iter().flatten().collect(),
+            },
+        })
+    }
+    pub fn at(&self, row: usize, col: usize) -> Option<u8> {
+        self.heights.get(row, col).copied()
+    }
+    pub fn heights(&self) -> &Grid<u8> {
+        &self.heights
     }
-
-    pub fn visibility_score(&self, grid: &TreeGrid) -> usize {
-        let score = self.visibility_to_bot(grid)
-            * self.visibility_to_left(grid)
-            * self.visibility_to_right(grid)
-            * self.visibility_to_top(grid);
-        debug!("Visibility score for tree {}: {}", self, score);
-        score
+    pub fn height(&self) -> usize {
+        self.heights.height()
     }
-
-    fn visibility_to_left(&self, grid: &TreeGrid) -> Visibility {
-        if self.col == 0 {
-            return Visibility {
-                range: 0,
-                to_edge: true,
-            };
-        }
-
-        let mut i = 0;
-        for left in (0..=self.col - 1).rev() {
-            match self.higher_than(grid, self.row, left) {
-                Some(true) => {
-                    i += 1;
-                }
-                Some(false) => {
-                    return Visibility {
-                        range: i + 1,
-                        to_edge: false,
-                    }
-                }
-                None => break,
-            }
-        }
-        Visibility {
-            range: i,
-            to_edge: true,
-        }
+    pub fn width(&self) -> usize {
+        self.heights.width()
     }
 
-    fn visibility_to_right(&self, grid: &TreeGrid) -> Visibility {
-        let mut i = 0;
-        for right in self.col + 1.. {
-            match self.higher_than(grid, self.row, right) {
-                Some(true) => {
-                    i += 1;
-                }
-                Some(false) => {
-                    return Visibility {
-                        range: i + 1,
-                        to_edge: false,
-                    }
-                }
-                None => break,
-            }
-        }
-        Visibility {
-            range: i,
-            to_edge: true,
-        }
+    /// Work out what every tree can see, looking in the directions given by `rules`
+    pub fn views(&self, rules: &ViewRules) -> Views {
+        Views::compute(&self.heights, rules)
     }
-
-    fn visibility_to_top(&self, grid: &TreeGrid) -> Visibility {
-        if self.row == 0 {
-            return Visibility {
-                range: 0,
-                to_edge: true,
-            };
-        }
-
-        let mut i = 0;
-        for top in (0..=self.row - 1).rev() {
-            match self.higher_than(grid, top, self.col) {
-                Some(true) => {
-                    i += 1;
-                }
-                Some(false) => {
-                    return Visibility {
-                        range: i + 1,
-                        to_edge: false,
-                    }
-                }
-                None => break,
+}
+impl Display for TreeGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.heights.rows() {
+            for h in row {
+                write!(f, "{}", h)?;
             }
+            writeln!(f)?;
         }
-        Visibility {
-            range: i,
-            to_edge: true,
-        }
+        Ok(())
     }
+}
 
-    fn visibility_to_bot(&self, grid: &TreeGrid) -> Visibility {
-        let mut i = 0;
-        for bot in self.row + 1.. {
-            match self.higher_than(grid, bot, self.col) {
-                Some(true) => {
-                    i += 1;
-                }
-                Some(false) => {
-                    return Visibility {
-                        range: i + 1,
-                        to_edge: false,
-                    }
-                }
-                None => break,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ragged_rows() {
+        assert_eq!(
+            TreeGrid::new(vec![vec![1, 2], vec![3]]).unwrap_err(),
+            GridError::RaggedRow {
+                row: 1,
+                len: 1,
+                expected: 2
             }
-        }
-        Visibility {
-            range: i,
-            to_edge: true,
-        }
-    }
-
-    fn higher_than(&self, grid: &TreeGrid, row: usize, col: usize) -> Option<bool> {
-        grid.at(row, col).map(|t| t.height < self.height)
-    }
-}
-impl Display for Tree {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({},{})", self.row, self.col)
+        );
+        let grid = TreeGrid::new(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        assert_eq!(grid.at(1, 0), Some(3));
+        assert_eq!(grid.at(0, 2), None);
+        assert_eq!(grid.to_string(), "12\n34\n");
     }
 }
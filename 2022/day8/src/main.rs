@@ -1,35 +1,14 @@
-use std::{collections::HashMap, fmt::Display, fs, hash::Hash};
+use std::{collections::HashMap, fmt::Display, fs};
 
 use color_eyre::{
     eyre::{eyre, Context, ContextCompat},
     Result,
 };
-use colored::{Color, ColoredString, Colorize};
-use day8::grid::TreeGrid;
+use colored::{Color, Colorize};
+use day8::{grid::TreeGrid, view::ViewRules};
 use itertools::Itertools;
 use log::debug;
 
-struct Visibility(Vec<Vec<bool>>);
-impl Display for Visibility {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for row in &self.0 {
-            for v in row {
-                write!(
-                    f,
-                    "{}",
-                    match v {
-                        true => "X",
-                        false => "-",
-                    }
-                )?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
-    }
-}
-
-struct VisibilityScore(Vec<Vec<usize>>);
 struct VisibilityColor(Vec<Vec<Color>>);
 impl Display for VisibilityColor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -62,42 +41,22 @@ fn main() -> Result<()> {
         rows.push(rvec);
     }
 
-    let grid = TreeGrid::new(rows);
+    let grid = TreeGrid::new(rows)?;
+    let views = grid.views(&ViewRules::orthogonal());
 
-    let mut vis_map = Visibility(Vec::with_capacity(grid.height()));
-    for row in 0..grid.height() {
-        let mut vis_row = Vec::with_capacity(grid.width());
-        for col in 0..grid.width() {
-            match grid.at(row, col) {
-                Some(t) => vis_row.push(t.is_visible(&grid)),
-                None => vis_row.push(false),
-            }
-        }
-        vis_map.0.push(vis_row);
-    }
-
-    println!("Visibility:\n{}", vis_map);
-
-    let total: usize = vis_map.0.iter().flatten().map(|v| usize::from(*v)).sum();
-    println!("Total amount of visible trees: {}", total);
-
-    let mut score_map = VisibilityScore(Vec::with_capacity(grid.height()));
-    for row in 0..grid.height() {
-        let mut score_row = Vec::with_capacity(grid.width());
-        for col in 0..grid.width() {
-            match grid.at(row, col) {
-                Some(t) => score_row.push(t.visibility_score(&grid)),
-                None => score_row.push(0),
-            }
-        }
-        score_map.0.push(score_row);
-    }
+    let vis_map = views.visible().wrap_err("No directions to look in")?;
+    println!(
+        "Visibility:\n{}",
+        vis_map.render(|v| if *v { 'X' } else { '-' })
+    );
+    println!("Total amount of visible trees: {}", views.visible_count());
 
-    let vis_scores = score_map.0.iter().flatten().sorted().collect_vec();
+    let score_map = views.scenic_scores().wrap_err("No directions to look in")?;
+    let vis_scores = score_map.iter().sorted().collect_vec();
     let highest = vis_scores.last().unwrap();
     println!("Highest Visibility: {}", highest);
 
-    let colors = vec![Color::Red, Color::Yellow, Color::Green, Color::Blue];
+    let colors = [Color::Red, Color::Yellow, Color::Green, Color::Blue];
     let unique_scores = vis_scores.iter().unique().collect_vec();
     let color_ranges: HashMap<_, &Color> = unique_scores
         .chunks(unique_scores.len() / colors.len())
@@ -115,7 +74,7 @@ fn main() -> Result<()> {
     for row in 0..grid.height() {
         let mut color_row: Vec<Color> = Vec::with_capacity(grid.width());
         for col in 0..grid.width() {
-            match score_map.0.get(row).unwrap_or(&vec![]).get(col) {
+            match score_map.get(row, col) {
                 Some(score) => {
                     for r in &color_ranges {
                         if r.0.contains(&&&score) {
@@ -132,5 +91,12 @@ fn main() -> Result<()> {
 
     println!("Visibility Score:\n{}", color_map);
 
+    let diagonal = grid.views(&ViewRules::with_diagonals());
+    println!(
+        "Including diagonals: {} visible trees, highest visibility {}",
+        diagonal.visible_count(),
+        diagonal.best_scenic_score()
+    );
+
     Ok(())
 }
@@ -0,0 +1,193 @@
+use log::debug;
+
+use crate::grid::Grid;
+
+/// A direction to look in, as (row, column) steps
+pub type Direction = (isize, isize);
+
+pub const UP: Direction = (-1, 0);
+pub const DOWN: Direction = (1, 0);
+pub const LEFT: Direction = (0, -1);
+pub const RIGHT: Direction = (0, 1);
+
+/// The directions in which trees can see and be seen
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ViewRules {
+    pub directions: Vec<Direction>,
+}
+impl ViewRules {
+    /// Up, down, left and right, as in the puzzle
+    pub fn orthogonal() -> Self {
+        ViewRules {
+            directions: vec![UP, DOWN, LEFT, RIGHT],
+        }
+    }
+
+    /// The orthogonal directions plus all four diagonals
+    pub fn with_diagonals() -> Self {
+        let mut rules = Self::orthogonal();
+        rules
+            .directions
+            .extend([(-1, -1), (-1, 1), (1, -1), (1, 1)]);
+        rules
+    }
+}
+impl Default for ViewRules {
+    fn default() -> Self {
+        Self::orthogonal()
+    }
+}
+
+/// What every tree sees when looking in a single direction
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DirectionView {
+    pub direction: Direction,
+    /// Whether the tree can be seen from the edge in this direction
+    pub visible: Grid<bool>,
+    /// Number of trees visible up to and including the first one that is at least as tall
+    pub distance: Grid<usize>,
+}
+
+/// What every tree sees, for all directions of a [`ViewRules`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Views {
+    pub directions: Vec<DirectionView>,
+}
+impl Views {
+    pub(crate) fn compute(heights: &Grid<u8>, rules: &ViewRules) -> Self {
+        Views {
+            directions: rules.directions.iter().map(|d| look(heights, *d)).collect(),
+        }
+    }
+
+    /// Trees that can be seen from at least one edge
+    pub fn visible(&self) -> Option<Grid<bool>> {
+        self.directions
+            .iter()
+            .map(|v| v.visible.clone())
+            .reduce(|acc, v| acc.zip_with(&v, |a, b| *a || *b))
+    }
+
+    /// The product of all viewing distances of each tree
+    pub fn scenic_scores(&self) -> Option<Grid<usize>> {
+        self.directions
+            .iter()
+            .map(|v| v.distance.clone())
+            .reduce(|acc, v| acc.zip_with(&v, |a, b| a * b))
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible()
+            .map_or(0, |g| g.iter().filter(|v| **v).count())
+    }
+
+    pub fn best_scenic_score(&self) -> usize {
+        self.scenic_scores()
+            .and_then(|g| g.iter().max().copied())
+            .unwrap_or(0)
+    }
+}
+
+/// Compute visibility and viewing distances for one direction.
+///
+/// Every line of sight is walked backwards from the edge it points to, keeping a stack of the
+/// trees ahead that aren't hidden behind a taller one. Each tree is pushed and popped at most once,
+/// so the whole grid takes linear time per direction
+fn look(heights: &Grid<u8>, direction: Direction) -> DirectionView {
+    let (rows, cols) = (heights.height(), heights.width());
+    let mut visible = Grid::filled(rows, cols, false);
+    let mut distance = Grid::filled(rows, cols, 0);
+
+    let mut stack: Vec<(u8, usize)> = Vec::new();
+    for line in lines(rows, cols, direction) {
+        stack.clear();
+        let last = line.len() - 1;
+        for (i, &(row, col)) in line.iter().enumerate().rev() {
+            let height = heights.get(row, col).copied().unwrap();
+            while stack.last().is_some_and(|(h, _)| *h < height) {
+                stack.pop();
+            }
+            let (seen, dist) = match stack.last() {
+                Some((_, blocker)) => (false, blocker - i),
+                None => (true, last - i),
+            };
+            *visible.get_mut(row, col).unwrap() = seen;
+            *distance.get_mut(row, col).unwrap() = dist;
+            stack.push((height, i));
+        }
+    }
+    debug!("Computed view in direction {:?}", direction);
+
+    DirectionView {
+        direction,
+        visible,
+        distance,
+    }
+}
+
+/// All lines of cells running in `direction`, each starting at the opposite edge
+fn lines(rows: usize, cols: usize, direction: Direction) -> Vec<Vec<(usize, usize)>> {
+    let inside =
+        |r: isize, c: isize| r >= 0 && c >= 0 && (r as usize) < rows && (c as usize) < cols;
+    let (dr, dc) = direction;
+    let mut lines = Vec::new();
+    for row in 0..rows as isize {
+        for col in 0..cols as isize {
+            // Only start where the previous cell would be outside the grid
+            if inside(row - dr, col - dc) {
+                continue;
+            }
+            let mut line = Vec::new();
+            let (mut r, mut c) = (row, col);
+            while inside(r, c) {
+                line.push((r as usize, c as usize));
+                r += dr;
+                c += dc;
+            }
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::TreeGrid;
+
+    fn example() -> TreeGrid {
+        let rows = ["30373", "25512", "65332", "33549", "35390"]
+            .iter()
+            .map(|r| r.bytes().map(|b| b - b'0').collect())
+            .collect();
+        TreeGrid::new(rows).unwrap()
+    }
+
+    #[test]
+    fn example_views() {
+        let views = example().views(&ViewRules::orthogonal());
+        assert_eq!(views.visible_count(), 21);
+        assert_eq!(views.best_scenic_score(), 8);
+        assert_eq!(
+            views
+                .visible()
+                .unwrap()
+                .render(|v| if *v { 'X' } else { '-' }),
+            "XXXXX\nXXX-X\nXX-XX\nX-X-X\nXXXXX\n"
+        );
+        let up = &views.directions[0];
+        assert_eq!(up.distance.get(3, 2), Some(&2));
+        assert_eq!(up.visible.get(3, 2), Some(&false));
+    }
+
+    #[test]
+    fn diagonal_views() {
+        let grid = TreeGrid::new(vec![vec![1, 1, 1], vec![1, 0, 1], vec![1, 1, 2]]).unwrap();
+        assert_eq!(grid.views(&ViewRules::orthogonal()).visible_count(), 8);
+        let views = grid.views(&ViewRules::with_diagonals());
+        // The corner tree looks over the middle one to the opposite corner
+        let up_left = views.directions.iter().find(|v| v.direction == (-1, -1));
+        assert_eq!(up_left.unwrap().distance.get(2, 2), Some(&2));
+        assert_eq!(views.scenic_scores().unwrap().get(1, 1), Some(&1));
+    }
+}
@@ -1,155 +1,109 @@
-use std::collections::HashSet;
-use std::f64::consts::SQRT_2;
-use std::fs::read_to_string;
-use std::str::Split;
-
-// const FILE_PATH: &str = "./inputs/day9.txt";
-const FILE_PATH: &str = "./inputs/test9.txt";
-
-#[derive(Debug)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+use std::{error::Error, fs::read_to_string};
+
+use day9::moves::Move;
+use day9::rope::Rope;
+
+fn collect_movement_data(input: &str) -> Result<Vec<Move>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, s)| !s.is_empty())
+        .map(|(i, s)| {
+            Move::try_from(s).map_err(|e| format!("line {}: `{}` is invalid: {}", i + 1, s, e))
+        })
+        .collect()
 }
 
-fn determine_direction(dir: &str) -> Direction {
-    match dir {
-        "U" => Direction::Up,
-        "D" => Direction::Down,
-        "L" => Direction::Left,
-        "R" => Direction::Right,
-        _ => panic!("string {dir} is invalid!"),
+fn tail_positions(data: &[Move], knots: usize) -> usize {
+    // [0] is the head, [knots - 1] is the tail
+    let mut rope = Rope::with_length(knots);
+    for m in data {
+        rope.move_head(m);
     }
-}
-
-fn collect_movement_data(input: Split<&str>) -> Vec<(Direction, i32)> {
-    let mut data: Vec<(Direction, i32)> = Vec::new();
 
-    for s in input {
-        if s.is_empty() {
-            continue;
-        }
-        let movement_data = s.split(" ").collect::<Vec<&str>>();
+    rope.visited_count_tail()
+}
 
-        let dir = determine_direction(movement_data[0]);
-        let amount = movement_data[1].parse::<i32>().unwrap();
-        data.push((dir, amount));
-    }
+fn main() -> Result<(), Box<dyn Error>> {
+    let input = read_to_string("input.txt")?;
+    let data = collect_movement_data(&input)?;
 
-    data
+    println!("{}", tail_positions(&data, 2));
+    println!("{}", tail_positions(&data, 10));
+    Ok(())
 }
 
-fn euclidean_2d(first: (i32, i32), second: (i32, i32)) -> f64 {
-    /*
-    Gets the 2d euclidean distance from two tuples
-    */
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, f64::consts::SQRT_2};
 
-    let xs = (second.0 - first.0).pow(2);
-    let ys = (second.1 - first.1).pow(2);
-    let total = (xs + ys) as f64;
+    use day9::moves::Direction;
 
-    total.sqrt()
-}
-
-fn determine_movement_vector(dir: Direction) -> (i32, i32) {
-    match dir {
-        Direction::Up => (0, 1),
-        Direction::Down => (0, -1),
-        Direction::Left => (-1, 0),
-        Direction::Right => (1, 0),
-    }
-}
+    use super::*;
 
-pub fn unique_positions() -> i32 {
-    let input = read_to_string("input.txt").unwrap();
-    let data = collect_movement_data(input.split("\n"));
+    const EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2\n";
+    const LARGER_EXAMPLE: &str = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20\n";
 
-    let mut unique_pos: HashSet<(i32, i32)> = HashSet::new();
+    fn euclidean_2d(first: (i32, i32), second: (i32, i32)) -> f64 {
+        let xs = (second.0 - first.0).pow(2);
+        let ys = (second.1 - first.1).pow(2);
+        ((xs + ys) as f64).sqrt()
+    }
 
-    let mut head_pos = (0, 0);
-    let mut tail_pos = (0, 0);
-    let mut prev_head_pos = (0, 0);
-    // iterates over all directions and amounts
-    for (dir, n) in data {
-        // println!("({:?}, {n})", dir);
-        // determines the way to move
-        let movement = determine_movement_vector(dir);
-        for _ in 0..n {
-            // if the distance between the two is greater than the SQRT(2)
-            //  move the tail
+    /// The original two-knot version: the tail jumps to where the head was whenever they are more
+    /// than SQRT(2) apart
+    fn euclidean_tail_positions(data: &[Move]) -> usize {
+        let mut unique_pos: HashSet<(i32, i32)> = HashSet::new();
+        let mut head_pos = (0, 0);
+        let mut tail_pos = (0, 0);
+        let mut prev_head_pos = (0, 0);
+        for m in data {
+            let movement = match m.direction {
+                Direction::Up => (0, 1),
+                Direction::Down => (0, -1),
+                Direction::Left => (-1, 0),
+                Direction::Right => (1, 0),
+            };
+            for _ in 0..m.amount {
+                if euclidean_2d(head_pos, tail_pos) > SQRT_2 {
+                    tail_pos = prev_head_pos;
+                }
+                unique_pos.insert(tail_pos);
+                prev_head_pos = head_pos;
+                head_pos.0 += movement.0;
+                head_pos.1 += movement.1;
+            }
             if euclidean_2d(head_pos, tail_pos) > SQRT_2 {
                 tail_pos = prev_head_pos;
             }
-            // adds the current tail position to the set
             unique_pos.insert(tail_pos);
-
-            // moves the head
-            prev_head_pos = head_pos;
-            head_pos.0 += movement.0;
-            head_pos.1 += movement.1;
-            // println!("\tHead: {:?}\n\tPrev: {:?}\n\tTail: {:?}", head_pos, prev_head_pos, tail_pos);
-        }
-
-        // checks to move the tail position one last time and adds it to the set
-        if euclidean_2d(head_pos, tail_pos) > SQRT_2 {
-            tail_pos = prev_head_pos;
         }
-        unique_pos.insert(tail_pos);
+        unique_pos.len()
     }
 
-    unique_pos.len() as i32
-}
-
-pub fn unique_positions_ten_knots() -> i32 {
-    let input = read_to_string("input.txt").unwrap();
-    let data = collect_movement_data(input.split("\n"));
-
-    let mut unique_pos: HashSet<(i32, i32)> = HashSet::new();
-
-    // creates a vector of 10 rope knots, with different positions and different previous positions.
-    // [0] is the head, [9] is the tail
-    let x = vec![((0, 0), (0, 0))];
-    // Generate a vec of (current,previous) position tuples
-    let mut rope_pos = x.into_iter().cycle().take(10).collect::<Vec<_>>();
-    for (dir, n) in data {
-        println!("Moving {} steps {:?}", n, dir);
-        // determines the way to move
-        let movement = determine_movement_vector(dir);
-        println!("Moving head this way: {:?}", movement);
-        for _ in 0..n {
-            // Save the old position in previous
-            rope_pos[0].1 = rope_pos[0].0;
-
-            // Update current position
-            rope_pos[0].0 .0 += movement.0;
-            rope_pos[0].0 .1 += movement.1;
-            println!(
-                "Head Position updated: {:?}->{:?}",
-                rope_pos[0].1, rope_pos[0].0
-            );
-
-            for i in 1..rope_pos.len() {
-                if euclidean_2d(rope_pos[i].0, rope_pos[i - 1].0) > SQRT_2 {
-                    rope_pos[i].1 = rope_pos[i].0;
-                    rope_pos[i].0 = rope_pos[i - 1].1;
-                    println!(
-                        "Node {} is too far away and must move: {:?}->{:?}",
-                        i, rope_pos[i].1, rope_pos[i].0
-                    );
-                }
-            }
-            println!("Processed follower nodes");
-            // println!("{:?}", rope_pos);
-            unique_pos.insert(rope_pos[9].0);
+    #[test]
+    fn agrees_with_the_euclidean_version() {
+        for input in [EXAMPLE, LARGER_EXAMPLE] {
+            let data = collect_movement_data(input).unwrap();
+            assert_eq!(tail_positions(&data, 2), euclidean_tail_positions(&data));
         }
     }
 
-    unique_pos.len() as i32
-}
+    #[test]
+    fn ten_knots() {
+        // The original ten-knot version moved every knot to where its leader had been, which
+        // only works for two knots. Check against the puzzle's answers instead
+        let data = collect_movement_data(EXAMPLE).unwrap();
+        assert_eq!(tail_positions(&data, 10), 1);
+        let data = collect_movement_data(LARGER_EXAMPLE).unwrap();
+        assert_eq!(tail_positions(&data, 10), 36);
+    }
 
-fn main() {
-    let out = unique_positions_ten_knots();
-    println!("{}", out);
+    #[test]
+    fn invalid_input() {
+        assert_eq!(
+            collect_movement_data("R 4\nX 1\n").unwrap_err(),
+            "line 2: `X 1` is invalid: Direction invalid (must be U,D,L,R)"
+        );
+    }
 }
@@ -22,7 +22,7 @@ fn main() -> Result<()> {
     let mut rope = Rope::new();
     for m in &moves {
         rope.move_head(m);
-        debug!("{:?}", rope.segment_positions());
+        debug!("{:?}", rope.knots());
     }
     println!(
         "2-rope Tail: Visited unique Locations: {}",
@@ -32,7 +32,10 @@ fn main() -> Result<()> {
     let mut loooong_rope = Rope::with_length(10);
     for m in &moves {
         loooong_rope.move_head(m);
-        debug!("Current Segment Locations (Head->...->Tail) {:?}", loooong_rope.segment_positions());
+        debug!(
+            "Current Knot Locations (Head->...->Tail) {:?}",
+            loooong_rope.knots()
+        );
     }
     println!(
         "10-rope Tail: Visited unique Locations: {}",
@@ -1,20 +1,99 @@
-use std::{
-    collections::{HashSet, VecDeque},
-    fmt::Display,
-    hash::Hash,
-    vec,
-};
+use std::{collections::HashSet, fmt::Display};
 
 use log::{debug, trace};
 
 use crate::moves::{Direction, Move};
 
+#[derive(Debug, Hash, PartialEq, Eq, Default, Clone, Copy)]
+pub struct Cell {
+    pub x: i32,
+    pub y: i32,
+}
+impl Cell {
+    fn step(&self, direction: Direction) -> Cell {
+        let (dx, dy) = match direction {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        };
+        Cell {
+            x: self.x + dx,
+            y: self.y + dy,
+        }
+    }
+}
+impl Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({},{})", self.x, self.y)
+    }
+}
+
+/// When a knot counts as touching the knot in front of it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Adjacency {
+    /// Touching includes diagonal neighbours
+    Chebyshev,
+    /// Only the four direct neighbours are touching
+    Manhattan,
+}
+impl Adjacency {
+    fn touching(&self, a: &Cell, b: &Cell) -> bool {
+        let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+        match self {
+            Adjacency::Chebyshev => dx.max(dy) <= 1,
+            Adjacency::Manhattan => dx + dy <= 1,
+        }
+    }
+}
+
+/// How a knot catches up with the knot in front of it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct FollowRule {
+    pub adjacency: Adjacency,
+    /// Whether a knot may move diagonally. If not, it moves along the axis with the larger
+    /// distance, possibly taking several steps to catch up. A diagonal move that would land on
+    /// the leader, as can happen with [`Adjacency::Manhattan`], is replaced by an orthogonal one
+    pub diagonal_moves: bool,
+}
+impl Default for FollowRule {
+    /// The rules from the puzzle
+    fn default() -> Self {
+        FollowRule {
+            adjacency: Adjacency::Chebyshev,
+            diagonal_moves: true,
+        }
+    }
+}
+impl FollowRule {
+    /// The next cell on the way from `knot` to `leader`, or `None` if they are already touching
+    fn next_step(&self, knot: &Cell, leader: &Cell) -> Option<Cell> {
+        if self.adjacency.touching(knot, leader) {
+            return None;
+        }
+        let (dx, dy) = (leader.x - knot.x, leader.y - knot.y);
+        let lands_on_leader = dx.abs() == 1 && dy.abs() == 1;
+        let (sx, sy) = if self.diagonal_moves && !lands_on_leader {
+            (dx.signum(), dy.signum())
+        } else if dx.abs() >= dy.abs() {
+            (dx.signum(), 0)
+        } else {
+            (0, dy.signum())
+        };
+        Some(Cell {
+            x: knot.x + sx,
+            y: knot.y + sy,
+        })
+    }
+}
+
+/// A rope made of knots, with the head at index 0 and the tail at the end
 #[derive(Debug, Clone)]
 pub struct Rope {
-    head: Cell,
-    head_visited: HashSet<Cell>,
-    next: Segment,
-    length: usize,
+    knots: Vec<Cell>,
+    /// Cells visited by each knot, including the starting cell
+    visited: Vec<HashSet<Cell>>,
+    rule: FollowRule,
 }
 
 impl Rope {
@@ -24,77 +103,84 @@ impl Rope {
 
     /// Create a longer rope. Will `panic!()` if length is less than 2
     pub fn with_length(length: usize) -> Self {
+        Rope::with_rule(length, FollowRule::default())
+    }
+
+    /// Create a rope whose knots follow each other according to `rule`.
+    /// Will `panic!()` if length is less than 2
+    pub fn with_rule(length: usize, rule: FollowRule) -> Self {
         if length < 2 {
-            panic!()
+            panic!("A rope needs at least 2 knots, got {}", length)
         }
-        let mut r = Rope {
-            head: Cell { x: 0, y: 0 },
-            next: Segment::new(Cell { x: 0, y: 0 }),
-            head_visited: HashSet::new(),
-            length,
-        };
-        if length > 2 {
-            for _ in 0..length - 1 {
-                r.next.add_segment();
-            }
+        let start = Cell::default();
+        Rope {
+            knots: vec![start; length],
+            visited: vec![HashSet::from([start]); length],
+            rule,
         }
-        r
     }
 
     pub fn move_head(&mut self, mov: &Move) {
         for _ in 0..mov.amount {
-            self.move_head_by_1(mov.direction);
-            self.next.follow(&self.head);
+            self.step(mov.direction);
         }
     }
 
-    fn move_head_by_1(&mut self, direction: Direction) {
-        let new_head = match direction {
-            Direction::Up => Cell {
-                x: self.head.x,
-                y: self.head.y + 1,
-            },
-            Direction::Down => Cell {
-                x: self.head.x,
-                y: self.head.y - 1,
-            },
-            Direction::Left => Cell {
-                x: self.head.x - 1,
-                y: self.head.y,
-            },
-            Direction::Right => Cell {
-                x: self.head.x + 1,
-                y: self.head.y,
-            },
-        };
-        debug!("Moved Head {} -> {}", self.head, new_head);
-        self.head_visited.insert(new_head);
-        self.head = new_head;
+    /// Move the head by one cell and let all other knots catch up
+    pub fn step(&mut self, direction: Direction) {
+        let new_head = self.knots[0].step(direction);
+        debug!("Moved Head {} -> {}", self.knots[0], new_head);
+        self.knots[0] = new_head;
+        self.visited[0].insert(new_head);
+
+        for i in 1..self.knots.len() {
+            let leader = self.knots[i - 1];
+            let mut moved = false;
+            while let Some(next) = self.rule.next_step(&self.knots[i], &leader) {
+                trace!("Knot {} moves {} -> {}", i, self.knots[i], next);
+                self.knots[i] = next;
+                self.visited[i].insert(next);
+                moved = true;
+            }
+            if !moved {
+                // Knots further back can only move if this one did
+                break;
+            }
+        }
     }
 
-    pub fn visited_count_head(&self) -> usize {
-        self.head_visited.len()
+    pub fn rule(&self) -> FollowRule {
+        self.rule
     }
 
-    pub fn visited_count_tail(&self) -> usize {
-        self.visited_count_segment(self.length - 1)
+    /// Current knot positions, head first
+    pub fn knots(&self) -> &[Cell] {
+        &self.knots
     }
 
-    pub fn visited_count_segment(&self, segment: usize) -> usize {
-        if self.length <= segment {
-            panic!()
-        }
-        if segment == 0 {
-            self.head_visited.len()
-        } else {
-            self.next.visited_count(segment, 1)
-        }
+    pub fn head(&self) -> Cell {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Cell {
+        self.knots[self.knots.len() - 1]
+    }
+
+    /// Cells visited by the given knot. Will `panic!()` if the knot does not exist
+    pub fn visited(&self, knot: usize) -> &HashSet<Cell> {
+        &self.visited[knot]
+    }
+
+    pub fn visited_count(&self, knot: usize) -> usize {
+        self.visited(knot).len()
+    }
+
+    pub fn visited_count_head(&self) -> usize {
+        self.visited_count(0)
     }
 
-    pub fn segment_positions(&self) -> Vec<Cell> {
-        let mut positions = vec![self.head];
-        positions.extend(self.next.segment_positions());
-        positions
+    pub fn visited_count_tail(&self) -> usize {
+        self.visited_count(self.knots.len() - 1)
     }
 }
 
@@ -104,134 +190,82 @@ impl Default for Rope {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Default, Clone)]
-struct Segment {
-    pos: Cell,
-    next: Option<Box<Segment>>,
-    visited: HashSet<Cell>,
-}
-#[derive(Debug, Hash, PartialEq, Eq, Default, Clone, Copy)]
-pub struct Cell {
-    x: i32,
-    y: i32,
-}
-impl Display for Cell {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({},{})", self.x, self.y)
-    }
-}
-impl Segment {
-    pub fn new(pos: Cell) -> Self {
-        Segment {
-            pos,
-            next: None,
-            visited: vec![Cell { x: 0, y: 0 }]
-                .into_iter()
-                .collect::<HashSet<_>>(),
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn add_segment(&mut self) {
-        if self.next.is_none() {
-            self.next = Some(Box::new(Segment::new(self.pos)));
-        } else {
-            self.next.as_mut().unwrap().add_segment();
+    fn run(rope: &mut Rope, input: &str) {
+        for l in input.lines() {
+            rope.move_head(&Move::try_from(l).unwrap());
         }
     }
 
-    pub fn follow(&mut self, parent: &Cell) {
-        trace!("Parent at {}, we're at {}", parent, self.pos);
-        let x_diff = parent.x - self.pos.x;
-        let y_diff = parent.y - self.pos.y;
-        trace!("Calculated x/y differences: {},{}", x_diff, y_diff);
-        let new_pos = match (x_diff.abs(), y_diff.abs()) {
-            (0, 0) => {
-                trace!("Parent and Segment are on top of each other, not moving Segment");
-                self.pos
-            }
-            (0, 1) | (1, 0) | (1, 1) => {
-                trace!("Parent and Segment are next to each other, not moving Segment");
-                self.pos
-            }
-            (2, 0) | (0, 2) => {
-                trace!("Parent and Segment are in straight line, following with Segment");
-                // Can't just match on direction as a longer rope can swing back and forth
-                match (x_diff, y_diff) {
-                    (1.., 0) => Cell {
-                        x: self.pos.x + 1,
-                        y: self.pos.y,
-                    },
-                    (i32::MIN..=-1, 0) => Cell {
-                        x: self.pos.x - 1,
-                        y: self.pos.y,
-                    },
-                    (0, 1..) => Cell {
-                        x: self.pos.x,
-                        y: self.pos.y + 1,
-                    },
-                    (0, i32::MIN..=-1) => Cell {
-                        x: self.pos.x,
-                        y: self.pos.y - 1,
-                    },
-                    _ => unreachable!(),
-                }
-            }
-            (2, 1) | (1, 2) | (2, 2) => {
-                trace!("Parent is offset, following with Segment diagonally");
-                match (x_diff.is_negative(), y_diff.is_negative()) {
-                    (true, true) => Cell {
-                        x: self.pos.x - 1,
-                        y: self.pos.y - 1,
-                    },
-                    (true, false) => Cell {
-                        x: self.pos.x - 1,
-                        y: self.pos.y + 1,
-                    },
-                    (false, true) => Cell {
-                        x: self.pos.x + 1,
-                        y: self.pos.y - 1,
-                    },
-                    (false, false) => Cell {
-                        x: self.pos.x + 1,
-                        y: self.pos.y + 1,
-                    },
-                }
-            }
-            _ => unreachable!(),
-        };
-        if new_pos != self.pos {
-            debug!("Moved Segment {}->{}", self.pos, new_pos);
-            self.pos = new_pos;
-            self.visited.insert(new_pos);
-            if self.next.is_some() {
-                self.next.as_mut().unwrap().follow(&self.pos);
-            }
-        }
+    const EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+    const LARGER_EXAMPLE: &str = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20";
+
+    #[test]
+    fn examples() {
+        let mut short = Rope::new();
+        run(&mut short, EXAMPLE);
+        assert_eq!(short.visited_count_tail(), 13);
+
+        let mut long = Rope::with_length(10);
+        run(&mut long, EXAMPLE);
+        assert_eq!(long.visited_count_tail(), 1);
+        let mut long = Rope::with_length(10);
+        run(&mut long, LARGER_EXAMPLE);
+        assert_eq!(long.visited_count_tail(), 36);
+        assert_eq!(long.visited_count(0), long.visited(0).len());
     }
 
-    pub fn segment_positions(&self) -> VecDeque<Cell> {
-        match &self.next {
-            Some(n) => {
-                let mut p = n.segment_positions();
-                p.push_front(self.pos);
-                p
-            }
-            None => {
-                let mut p = VecDeque::new();
-                p.push_front(self.pos);
-                p
-            }
-        }
+    #[test]
+    fn orthogonal_knots() {
+        let mut rope = Rope::with_rule(
+            3,
+            FollowRule {
+                adjacency: Adjacency::Manhattan,
+                diagonal_moves: false,
+            },
+        );
+        run(&mut rope, "R 2\nU 1");
+        // The knots trail behind the head like a snake
+        assert_eq!(
+            rope.knots(),
+            &[
+                Cell { x: 2, y: 1 },
+                Cell { x: 2, y: 0 },
+                Cell { x: 1, y: 0 }
+            ]
+        );
+        assert_eq!(rope.visited_count_tail(), 2);
     }
 
-    pub fn visited_count(&self, wanted: usize, depth: usize) -> usize {
-        if wanted == depth {
-            self.visited.len()
-        } else {
-            match &self.next {
-                Some(n) => n.visited_count(wanted, depth + 1),
-                None => panic!(),
-            }
-        }
+    #[test]
+    fn manhattan_diagonal_moves_stop_next_to_the_leader() {
+        let rule = FollowRule {
+            adjacency: Adjacency::Manhattan,
+            diagonal_moves: true,
+        };
+        assert_eq!(
+            rule.next_step(&Cell { x: 0, y: 0 }, &Cell { x: 1, y: 1 }),
+            Some(Cell { x: 1, y: 0 })
+        );
+        assert_eq!(
+            rule.next_step(&Cell { x: 0, y: 0 }, &Cell { x: 2, y: 1 }),
+            Some(Cell { x: 1, y: 1 })
+        );
+
+        let mut rope = Rope::with_rule(3, rule);
+        run(
+            &mut rope,
+            "R 2
+U 1
+R 1",
+        );
+        let knots = rope.knots();
+        assert!(knots.windows(2).all(|k| k[0] != k[1]));
+        assert!(knots
+            .windows(2)
+            .all(|k| rule.adjacency.touching(&k[0], &k[1])));
     }
 }
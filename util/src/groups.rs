@@ -0,0 +1,124 @@
+use std::{
+    io::{self, BufRead},
+    iter::FusedIterator,
+    marker::PhantomData,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GroupError {
+    #[error("Reading input failed")]
+    Io(#[from] io::Error),
+    #[error("Line {line}: `{value}` is not a valid value")]
+    Parse { line: usize, value: String },
+}
+
+/// Groups of values, one per line, separated by blank lines. See [`groups`]
+///
+/// Iteration stops after the end of the input or the first error
+pub struct Groups<R, T> {
+    reader: R,
+    line: usize,
+    buf: String,
+    done: bool,
+    value: PhantomData<T>,
+}
+impl<R: BufRead, T: FromStr> Iterator for Groups<R, T> {
+    type Item = Result<Vec<T>, GroupError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_group();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+impl<R: BufRead, T: FromStr> FusedIterator for Groups<R, T> {}
+
+impl<R: BufRead, T: FromStr> Groups<R, T> {
+    fn read_group(&mut self) -> Option<Result<Vec<T>, GroupError>> {
+        let mut group = Vec::new();
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return (!group.is_empty()).then_some(Ok(group)),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+            self.line += 1;
+            // Also takes care of `\r\n` line endings
+            let value = self.buf.trim();
+            if value.is_empty() {
+                if group.is_empty() {
+                    // Skip repeated blank lines
+                    continue;
+                }
+                return Some(Ok(group));
+            }
+            match value.parse() {
+                Ok(v) => group.push(v),
+                Err(_) => {
+                    return Some(Err(GroupError::Parse {
+                        line: self.line,
+                        value: value.to_string(),
+                    }))
+                }
+            }
+        }
+    }
+}
+
+/// Read blank-line-separated groups of values from `reader`, one line at a time
+pub fn groups<T: FromStr, R: BufRead>(reader: R) -> Groups<R, T> {
+    Groups {
+        reader,
+        line: 0,
+        buf: String::new(),
+        done: false,
+        value: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings_and_whitespace() {
+        let input = "1\r\n2 \r\n\r\n\r\n3\n  \n4\n\n";
+        let parsed: Vec<Vec<u32>> = groups(input.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(parsed, vec![vec![1, 2], vec![3], vec![4]]);
+
+        let err = groups::<u32, _>("1\n\nx\n".as_bytes())
+            .find_map(Result::err)
+            .unwrap();
+        assert!(matches!(err, GroupError::Parse { line: 3, value } if value == "x"));
+    }
+
+    #[test]
+    fn stops_after_an_error() {
+        let mut parse = groups::<u32, _>("1\nx\n2\n\n3\n".as_bytes());
+        assert!(matches!(
+            parse.next(),
+            Some(Err(GroupError::Parse { line: 2, .. }))
+        ));
+        assert!(parse.next().is_none());
+        assert!(parse.next().is_none());
+
+        struct Broken;
+        impl io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+        let mut read = groups::<u32, _>(io::BufReader::new(Broken));
+        assert!(matches!(read.next(), Some(Err(GroupError::Io(_)))));
+        assert!(read.next().is_none());
+    }
+}
@@ -1 +1,3 @@
 pub mod grid;
+pub mod groups;
+pub mod taxicab;
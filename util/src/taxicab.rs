@@ -0,0 +1,306 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use itertools::Itertools;
+
+pub trait ManhattanGeometry {
+    type Distance;
+
+    fn taxicab_distance(&self, other: &Self) -> Self::Distance;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
+}
+impl Point {
+    pub fn new(x: isize, y: isize) -> Self {
+        Point { x, y }
+    }
+    /// Rotate the point by 45°, so that diamonds become axis-aligned squares
+    pub fn rotate(&self) -> RotatedPoint {
+        RotatedPoint {
+            u: self.x + self.y,
+            v: self.x - self.y,
+        }
+    }
+}
+impl ManhattanGeometry for Point {
+    type Distance = usize;
+
+    fn taxicab_distance(&self, other: &Self) -> Self::Distance {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+}
+impl Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({},{})", self.x, self.y)
+    }
+}
+
+/// A point in the 45°-rotated coordinate system, with `u = x + y` and `v = x - y`.
+///
+/// Taxicab distance in the original system is Chebyshev distance here. Only points where `u` and
+/// `v` have the same parity map back onto the integer grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RotatedPoint {
+    pub u: isize,
+    pub v: isize,
+}
+impl RotatedPoint {
+    /// Rotate back into the original system. Returns `None` if the point is between grid cells
+    pub fn unrotate(&self) -> Option<Point> {
+        if (self.u - self.v).rem_euclid(2) != 0 {
+            return None;
+        }
+        Some(Point {
+            x: (self.u + self.v) / 2,
+            y: (self.u - self.v) / 2,
+        })
+    }
+}
+
+/// An axis-aligned rectangle in the rotated coordinate system, which is a (possibly non-square)
+/// diamond in the original one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RotatedRect {
+    pub u: RangeInclusive<isize>,
+    pub v: RangeInclusive<isize>,
+}
+impl RotatedRect {
+    pub fn contains(&self, p: &RotatedPoint) -> bool {
+        self.u.contains(&p.u) && self.v.contains(&p.v)
+    }
+    pub fn intersection(&self, other: &RotatedRect) -> Option<RotatedRect> {
+        let u = intersect_ranges(&self.u, &other.u)?;
+        let v = intersect_ranges(&self.v, &other.v)?;
+        Some(RotatedRect { u, v })
+    }
+    /// Number of grid points of the original system inside the rectangle
+    pub fn area(&self) -> usize {
+        [0, 1]
+            .into_iter()
+            .map(|parity| {
+                let (u, v) = self.parity_class(parity);
+                range_len(&u) * range_len(&v)
+            })
+            .sum()
+    }
+
+    /// The points of the rectangle where both `u` and `v` have the given parity, mapped onto a
+    /// dense grid with `u = 2 * u' + parity`
+    fn parity_class(&self, parity: isize) -> (RangeInclusive<isize>, RangeInclusive<isize>) {
+        let compress = |r: &RangeInclusive<isize>| {
+            // ceil and floor of (bound - parity) / 2
+            (r.start() - parity + 1).div_euclid(2)..=(r.end() - parity).div_euclid(2)
+        };
+        (compress(&self.u), compress(&self.v))
+    }
+}
+
+/// All points within `radius` of `center`, also known as a L1 ball
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Diamond {
+    pub center: Point,
+    pub radius: usize,
+}
+impl Diamond {
+    pub fn new(center: Point, radius: usize) -> Self {
+        Diamond { center, radius }
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        self.center.taxicab_distance(p) <= self.radius
+    }
+
+    /// The cells of the diamond in row `y`
+    pub fn row(&self, y: isize) -> Option<RangeInclusive<isize>> {
+        let dy = self.center.y.abs_diff(y);
+        let half_width = self.radius.checked_sub(dy)? as isize;
+        Some(self.center.x - half_width..=self.center.x + half_width)
+    }
+
+    /// The cells of the diamond in column `x`
+    pub fn column(&self, x: isize) -> Option<RangeInclusive<isize>> {
+        let dx = self.center.x.abs_diff(x);
+        let half_height = self.radius.checked_sub(dx)? as isize;
+        Some(self.center.y - half_height..=self.center.y + half_height)
+    }
+
+    /// Number of cells inside the diamond
+    pub fn area(&self) -> usize {
+        2 * self.radius * self.radius + 2 * self.radius + 1
+    }
+
+    /// All cells at exactly `radius` from the center
+    pub fn border(&self) -> impl Iterator<Item = Point> {
+        let center = self.center;
+        let r = self.radius as isize;
+        (-r..=r).flat_map(move |dy| {
+            let dx = r - dy.abs();
+            [center.x - dx, center.x + dx]
+                .into_iter()
+                .dedup()
+                .map(move |x| Point {
+                    x,
+                    y: center.y + dy,
+                })
+        })
+    }
+
+    pub fn intersects(&self, other: &Diamond) -> bool {
+        self.center.taxicab_distance(&other.center) <= self.radius + other.radius
+    }
+
+    /// The diamond as a square in the rotated coordinate system
+    pub fn rotate(&self) -> RotatedRect {
+        let c = self.center.rotate();
+        let r = self.radius as isize;
+        RotatedRect {
+            u: c.u - r..=c.u + r,
+            v: c.v - r..=c.v + r,
+        }
+    }
+
+    /// The area covered by both diamonds, if any
+    pub fn intersection(&self, other: &Diamond) -> Option<RotatedRect> {
+        self.rotate()
+            .intersection(&other.rotate())
+            .filter(|r| r.area() > 0)
+    }
+}
+
+/// Number of grid cells covered by at least one of the diamonds
+pub fn union_area(diamonds: &[Diamond]) -> usize {
+    let rects = diamonds.iter().map(|d| d.rotate()).collect_vec();
+    // Grid cells map to rotated points where u and v are both even or both odd. Each class is a
+    // dense grid on its own, so we can count the union of plain rectangles in both.
+    [0, 1]
+        .into_iter()
+        .map(|parity| {
+            let classes = rects.iter().map(|r| r.parity_class(parity)).collect_vec();
+            rect_union_area(&classes)
+        })
+        .sum()
+}
+
+/// Number of integer points covered by the union of axis-aligned rectangles, using a sweep over
+/// the x boundaries and merged y intervals for every slab
+fn rect_union_area(rects: &[(RangeInclusive<isize>, RangeInclusive<isize>)]) -> usize {
+    let rects = rects
+        .iter()
+        .filter(|(x, y)| !x.is_empty() && !y.is_empty())
+        .collect_vec();
+    let xs = rects
+        .iter()
+        .flat_map(|(x, _)| [*x.start(), x.end() + 1])
+        .sorted()
+        .dedup()
+        .collect_vec();
+    xs.iter()
+        .tuple_windows()
+        .map(|(&x_start, &x_end)| {
+            let ys = rects
+                .iter()
+                .filter(|(x, _)| x.contains(&x_start))
+                .map(|(_, y)| y.clone())
+                .collect_vec();
+            x_start.abs_diff(x_end) * merged_len(ys)
+        })
+        .sum()
+}
+
+fn merged_len(mut ranges: Vec<RangeInclusive<isize>>) -> usize {
+    ranges.sort_by_key(|r| *r.start());
+    let mut total = 0;
+    let mut current: Option<RangeInclusive<isize>> = None;
+    for r in ranges {
+        current = match current {
+            Some(c) if *r.start() <= c.end() + 1 => Some(*c.start()..=*c.end().max(r.end())),
+            Some(c) => {
+                total += range_len(&c);
+                Some(r)
+            }
+            None => Some(r),
+        }
+    }
+    total + current.map(|c| range_len(&c)).unwrap_or(0)
+}
+
+fn range_len(r: &RangeInclusive<isize>) -> usize {
+    if r.is_empty() {
+        0
+    } else {
+        r.end().abs_diff(*r.start()) + 1
+    }
+}
+
+fn intersect_ranges(
+    a: &RangeInclusive<isize>,
+    b: &RangeInclusive<isize>,
+) -> Option<RangeInclusive<isize>> {
+    let r = *a.start().max(b.start())..=*a.end().min(b.end());
+    (!r.is_empty()).then_some(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force_area(diamonds: &[Diamond]) -> usize {
+        (-20..=20)
+            .cartesian_product(-20..=20)
+            .filter(|(x, y)| diamonds.iter().any(|d| d.contains(&Point::new(*x, *y))))
+            .count()
+    }
+
+    #[test]
+    fn rotation_roundtrip() {
+        let p = Point::new(3, -7);
+        assert_eq!(p.rotate().unrotate(), Some(p));
+        assert_eq!(RotatedPoint { u: 1, v: 0 }.unrotate(), None);
+    }
+
+    #[test]
+    fn slices() {
+        let d = Diamond::new(Point::new(8, 7), 9);
+        assert_eq!(d.row(10), Some(2..=14));
+        assert_eq!(d.row(16), Some(8..=8));
+        assert_eq!(d.row(17), None);
+        assert_eq!(d.column(6), Some(0..=14));
+        assert!(d.contains(&Point::new(2, 10)));
+        assert!(!d.contains(&Point::new(1, 10)));
+    }
+
+    #[test]
+    fn border() {
+        let d = Diamond::new(Point::new(0, 0), 2);
+        let border = d.border().collect_vec();
+        assert_eq!(border.len(), 8);
+        assert!(border.iter().all(|p| p.taxicab_distance(&d.center) == 2));
+    }
+
+    #[test]
+    fn areas() {
+        let a = Diamond::new(Point::new(0, 0), 3);
+        let b = Diamond::new(Point::new(4, 1), 2);
+        let c = Diamond::new(Point::new(-5, 6), 4);
+        assert_eq!(a.area(), brute_force_area(&[a]));
+        assert_eq!(a.rotate().area(), a.area());
+        assert_eq!(
+            a.intersection(&b).unwrap().area(),
+            brute_force_area(&[a]) + brute_force_area(&[b]) - brute_force_area(&[a, b])
+        );
+        assert_eq!(union_area(&[a, b, c]), brute_force_area(&[a, b, c]));
+    }
+
+    #[test]
+    fn touching_diamonds() {
+        // Distance 3 between centers, so the diamonds only touch diagonally
+        let a = Diamond::new(Point::new(0, 0), 1);
+        let b = Diamond::new(Point::new(2, 1), 1);
+        assert!(!a.intersects(&b));
+        assert_eq!(a.intersection(&b), None);
+        assert_eq!(union_area(&[a, b]), 10);
+    }
+}